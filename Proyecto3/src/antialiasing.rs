@@ -0,0 +1,318 @@
+// antialiasing.rs - Modos de antialiasing: multisampling con profundidad por muestra o FXAA
+//
+// Con multisampling cada píxel tiene varias muestras de color y profundidad (ver
+// Framebuffer::set_anti_aliasing); el triángulo se prueba en cada muestra pero el shader
+// corre una sola vez por píxel, así que el costo crece con los bordes y no con el área.
+// FXAA en cambio no toca el rasterizador: busca bordes en la imagen ya en pantalla y los
+// suaviza mezclando a lo largo de ellos.
+
+use std::time::Duration;
+use nalgebra_glm::Vec3;
+use crate::framebuffer::{pack_pixel, unpack_pixel, Framebuffer};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AntiAliasing {
+    // Una muestra en el centro del píxel
+    Off,
+    // 4 muestras en una rejilla de 2x2
+    OrderedGrid,
+    // 4 muestras en una rejilla girada (RGSS): mejor en los bordes casi horizontales o verticales
+    RotatedGrid,
+    // Filtro sobre la imagen final, casi gratis
+    Fxaa,
+}
+
+// Máximo de muestras por píxel de los modos de multisampling
+pub const MAX_SAMPLES: usize = 4;
+
+const CENTER: [(f32, f32); 1] = [(0.0, 0.0)];
+const ORDERED_GRID: [(f32, f32); 4] = [(-0.25, -0.25), (0.25, -0.25), (-0.25, 0.25), (0.25, 0.25)];
+const ROTATED_GRID: [(f32, f32); 4] = [(-0.125, -0.375), (0.375, -0.125), (0.125, 0.375), (-0.375, 0.125)];
+
+// Contraste local mínimo para que FXAA considere que hay un borde (absoluto y relativo)
+const FXAA_EDGE_THRESHOLD_MIN: f32 = 0.0312;
+const FXAA_EDGE_THRESHOLD: f32 = 0.125;
+// Cuánto suaviza los detalles de un píxel (0 = nada, 1 = mucho)
+const FXAA_SUBPIXEL: f32 = 0.75;
+// Pasos de la búsqueda del extremo del borde, cada vez más largos
+const FXAA_STEPS: [f32; 10] = [1.0, 1.0, 1.0, 1.0, 1.5, 2.0, 2.0, 2.0, 4.0, 8.0];
+
+impl AntiAliasing {
+    // Posiciones de las muestras respecto al centro del píxel, en píxeles
+    pub fn sample_offsets(&self) -> &'static [(f32, f32)] {
+        match self {
+            AntiAliasing::Off | AntiAliasing::Fxaa => &CENTER,
+            AntiAliasing::OrderedGrid => &ORDERED_GRID,
+            AntiAliasing::RotatedGrid => &ROTATED_GRID,
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            AntiAliasing::Off => AntiAliasing::OrderedGrid,
+            AntiAliasing::OrderedGrid => AntiAliasing::RotatedGrid,
+            AntiAliasing::RotatedGrid => AntiAliasing::Fxaa,
+            AntiAliasing::Fxaa => AntiAliasing::Off,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "off" => Some(AntiAliasing::Off),
+            "ordered" => Some(AntiAliasing::OrderedGrid),
+            "rotated" => Some(AntiAliasing::RotatedGrid),
+            "fxaa" => Some(AntiAliasing::Fxaa),
+            _ => None,
+        }
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            AntiAliasing::Off | AntiAliasing::Fxaa => format!("{:?}", self),
+            _ => format!("{:?} {}x", self, self.sample_offsets().len()),
+        }
+    }
+}
+
+// Tiempo medio por frame de la escena y del antialiasing desde el último cambio de modo
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AntiAliasingTimings {
+    frames: u32,
+    render: Duration,
+    anti_aliasing: Duration,
+}
+
+impl AntiAliasingTimings {
+    pub fn record(&mut self, render: Duration, anti_aliasing: Duration) {
+        self.frames += 1;
+        self.render += render;
+        self.anti_aliasing += anti_aliasing;
+    }
+
+    pub fn reset(&mut self) {
+        *self = AntiAliasingTimings::default();
+    }
+
+    // (escena, antialiasing) en milisegundos
+    pub fn average_ms(&self) -> (f32, f32) {
+        if self.frames == 0 {
+            return (0.0, 0.0);
+        }
+        let average = |total: Duration| total.as_secs_f32() * 1000.0 / self.frames as f32;
+        (average(self.render), average(self.anti_aliasing))
+    }
+
+    pub fn summary(&self, mode: AntiAliasing) -> String {
+        let (render, anti_aliasing) = self.average_ms();
+        format!("AA: {} | scene {:.1} ms | AA {:.2} ms", mode.label(), render, anti_aliasing)
+    }
+}
+
+// FXAA sobre `Framebuffer.buffer`; guarda las copias entre frames
+#[derive(Default)]
+pub struct Fxaa {
+    source: Vec<u32>,
+    luma: Vec<f32>,
+}
+
+impl Fxaa {
+    pub fn apply(&mut self, framebuffer: &mut Framebuffer) {
+        let (width, height) = (framebuffer.width, framebuffer.height);
+        self.source.clone_from(&framebuffer.buffer);
+        self.luma.clear();
+        self.luma.extend(self.source.iter().map(|&pixel| luma(unpack_pixel(pixel))));
+
+        let image = Image { width, height, pixels: &self.source, luma: &self.luma };
+        for y in 0..height {
+            for x in 0..width {
+                if let Some(color) = image.fxaa_pixel(x, y) {
+                    framebuffer.buffer[y * width + x] = pack_pixel(color);
+                }
+            }
+        }
+    }
+}
+
+struct Image<'a> {
+    width: usize,
+    height: usize,
+    pixels: &'a [u32],
+    luma: &'a [f32],
+}
+
+impl Image<'_> {
+    fn luma_at(&self, x: isize, y: isize) -> f32 {
+        let x = x.clamp(0, self.width as isize - 1) as usize;
+        let y = y.clamp(0, self.height as isize - 1) as usize;
+        self.luma[y * self.width + x]
+    }
+
+    // Interpolación bilineal con los centros de los píxeles en coordenadas enteras
+    fn bilinear<T: std::ops::Add<Output = T> + std::ops::Mul<f32, Output = T>>(&self, x: f32, y: f32, at: impl Fn(isize, isize) -> T) -> T {
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as isize, y0 as isize);
+        let top = at(x0, y0) * (1.0 - fx) + at(x0 + 1, y0) * fx;
+        let bottom = at(x0, y0 + 1) * (1.0 - fx) + at(x0 + 1, y0 + 1) * fx;
+        top * (1.0 - fy) + bottom * fy
+    }
+
+    fn color_at(&self, x: f32, y: f32) -> Vec3 {
+        self.bilinear(x, y, |x, y| {
+            let x = x.clamp(0, self.width as isize - 1) as usize;
+            let y = y.clamp(0, self.height as isize - 1) as usize;
+            unpack_pixel(self.pixels[y * self.width + x])
+        })
+    }
+
+    // Color suavizado del píxel, o None si no está en un borde
+    fn fxaa_pixel(&self, x: usize, y: usize) -> Option<Vec3> {
+        let (xi, yi) = (x as isize, y as isize);
+        let center = self.luma_at(xi, yi);
+        let north = self.luma_at(xi, yi - 1);
+        let south = self.luma_at(xi, yi + 1);
+        let west = self.luma_at(xi - 1, yi);
+        let east = self.luma_at(xi + 1, yi);
+
+        let luma_min = center.min(north).min(south).min(west).min(east);
+        let luma_max = center.max(north).max(south).max(west).max(east);
+        let range = luma_max - luma_min;
+        if range < FXAA_EDGE_THRESHOLD_MIN.max(luma_max * FXAA_EDGE_THRESHOLD) {
+            return None;
+        }
+
+        let north_west = self.luma_at(xi - 1, yi - 1);
+        let north_east = self.luma_at(xi + 1, yi - 1);
+        let south_west = self.luma_at(xi - 1, yi + 1);
+        let south_east = self.luma_at(xi + 1, yi + 1);
+
+        // Suavizado de subpíxel: cuánto se aleja el centro del promedio de sus vecinos
+        let average = (2.0 * (north + south + west + east) + north_west + north_east + south_west + south_east) / 12.0;
+        let subpixel = ((average - center).abs() / range).clamp(0.0, 1.0);
+        let subpixel = (-2.0 * subpixel + 3.0) * subpixel * subpixel;
+        let subpixel_offset = subpixel * subpixel * FXAA_SUBPIXEL;
+
+        // ¿El borde es horizontal (cambia de arriba a abajo) o vertical?
+        let edge_horizontal = (north_west + south_west - 2.0 * west).abs()
+            + 2.0 * (north + south - 2.0 * center).abs()
+            + (north_east + south_east - 2.0 * east).abs();
+        let edge_vertical = (north_west + north_east - 2.0 * north).abs()
+            + 2.0 * (west + east - 2.0 * center).abs()
+            + (south_west + south_east - 2.0 * south).abs();
+        let horizontal = edge_horizontal >= edge_vertical;
+
+        // Lado del borde con el cambio más fuerte
+        let (luma_negative, luma_positive) = if horizontal { (north, south) } else { (west, east) };
+        let gradient_negative = luma_negative - center;
+        let gradient_positive = luma_positive - center;
+        let negative_side = gradient_negative.abs() >= gradient_positive.abs();
+        let gradient_scaled = 0.25 * gradient_negative.abs().max(gradient_positive.abs());
+        let (step, local_average) = if negative_side {
+            (-1.0, 0.5 * (luma_negative + center))
+        } else {
+            (1.0, 0.5 * (luma_positive + center))
+        };
+
+        // Punto sobre el borde (medio píxel hacia el lado elegido) y dirección a lo largo de él
+        let (mut ux, mut uy) = (x as f32, y as f32);
+        if horizontal {
+            uy += step * 0.5;
+        } else {
+            ux += step * 0.5;
+        }
+        let (dx, dy) = if horizontal { (1.0, 0.0) } else { (0.0, 1.0) };
+        let edge_luma = |px: f32, py: f32| self.bilinear(px, py, |x, y| self.luma_at(x, y)) - local_average;
+
+        // Se avanza hacia los dos extremos hasta que la luminancia deja de ser la del borde
+        let (mut x1, mut y1, mut x2, mut y2) = (ux - dx, uy - dy, ux + dx, uy + dy);
+        let mut end1 = edge_luma(x1, y1);
+        let mut end2 = edge_luma(x2, y2);
+        for length in FXAA_STEPS {
+            let done1 = end1.abs() >= gradient_scaled;
+            let done2 = end2.abs() >= gradient_scaled;
+            if done1 && done2 {
+                break;
+            }
+            if !done1 {
+                x1 -= dx * length;
+                y1 -= dy * length;
+                end1 = edge_luma(x1, y1);
+            }
+            if !done2 {
+                x2 += dx * length;
+                y2 += dy * length;
+                end2 = edge_luma(x2, y2);
+            }
+        }
+
+        let (distance1, distance2) = if horizontal { (x as f32 - x1, x2 - x as f32) } else { (y as f32 - y1, y2 - y as f32) };
+        let closer_is_1 = distance1 < distance2;
+        let distance = distance1.min(distance2);
+        let edge_offset = 0.5 - distance / (distance1 + distance2);
+
+        // Solo se mezcla si el extremo más cercano confirma que el centro está del lado correcto
+        let center_smaller = center < local_average;
+        let end = if closer_is_1 { end1 } else { end2 };
+        let edge_offset = if (end < 0.0) != center_smaller { edge_offset } else { 0.0 };
+
+        let offset = edge_offset.max(subpixel_offset);
+        let (sx, sy) = if horizontal { (x as f32, y as f32 + offset * step) } else { (x as f32 + offset * step, y as f32) };
+        Some(self.color_at(sx, sy))
+    }
+}
+
+// Luminancia perceptual de un color sRGB (FXAA trabaja sobre la imagen ya codificada)
+fn luma(color: Vec3) -> f32 {
+    0.299 * color.x + 0.587 * color.y + 0.114 * color.z
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MODES: [AntiAliasing; 4] = [AntiAliasing::Off, AntiAliasing::OrderedGrid, AntiAliasing::RotatedGrid, AntiAliasing::Fxaa];
+
+    #[test]
+    fn sample_offsets_stay_inside_the_pixel() {
+        for mode in MODES {
+            let offsets = mode.sample_offsets();
+            assert!(!offsets.is_empty() && offsets.len() <= MAX_SAMPLES, "{:?}", mode);
+            assert!(offsets.iter().all(|&(dx, dy)| dx.abs() < 0.5 && dy.abs() < 0.5), "{:?}", mode);
+            // Centradas: el promedio de las muestras es el centro del píxel
+            let (sum_x, sum_y) = offsets.iter().fold((0.0, 0.0), |(x, y), &(dx, dy)| (x + dx, y + dy));
+            assert!(sum_x.abs() < 1e-6 && sum_y.abs() < 1e-6, "{:?}", mode);
+        }
+    }
+
+    #[test]
+    fn rotated_grid_uses_distinct_rows_and_columns() {
+        let offsets = AntiAliasing::RotatedGrid.sample_offsets();
+        for axis in [|o: &(f32, f32)| o.0, |o: &(f32, f32)| o.1] {
+            let mut values: Vec<f32> = offsets.iter().map(axis).collect();
+            values.sort_by(f32::total_cmp);
+            values.dedup();
+            assert_eq!(values.len(), offsets.len());
+        }
+    }
+
+    #[test]
+    fn modes_cycle_and_parse() {
+        let mut mode = AntiAliasing::Off;
+        for _ in 0..MODES.len() {
+            mode = mode.next();
+        }
+        assert_eq!(mode, AntiAliasing::Off);
+        for (name, mode) in ["off", "ordered", "rotated", "FXAA"].iter().zip(MODES) {
+            assert_eq!(AntiAliasing::from_name(name), Some(mode));
+        }
+        assert_eq!(AntiAliasing::from_name("msaa"), None);
+    }
+
+    #[test]
+    fn fxaa_leaves_flat_images_untouched() {
+        let mut framebuffer = Framebuffer::new(16, 16);
+        framebuffer.buffer.fill(0x3366CC);
+        Fxaa::default().apply(&mut framebuffer);
+        assert!(framebuffer.buffer.iter().all(|&pixel| pixel == 0x3366CC));
+    }
+}
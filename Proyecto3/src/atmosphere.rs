@@ -0,0 +1,184 @@
+// atmosphere.rs - Atmósferas: dispersión de Rayleigh y Mie a lo largo del rayo de vista
+//
+// La atmósfera es una esfera algo mayor que el cuerpo que se dibuja en la pasada
+// transparente con mezcla aditiva. Cada fragmento recorre el rayo de la cámara dentro de la
+// capa y suma la luz de las estrellas que se dispersa hacia el ojo, atenuada por el aire
+// que atraviesa en el camino de ida y de vuelta. Las distancias se miden en espesores de
+// la capa, así que `density` se comporta igual en planetas de cualquier tamaño.
+
+use std::f32::consts::PI;
+use nalgebra_glm::Vec3;
+use serde::Deserialize;
+use crate::light::Light;
+use crate::shadow::Occluder;
+
+const VIEW_SAMPLES: usize = 12;
+const LIGHT_SAMPLES: usize = 4;
+// Fracción del espesor de la capa en la que la densidad cae a 1/e
+const RAYLEIGH_SCALE_HEIGHT: f32 = 0.25;
+const MIE_SCALE_HEIGHT: f32 = 0.1;
+// Los aerosoles dispersan sin color, sobre todo hacia delante (halo a contraluz)
+const MIE_STRENGTH: f32 = 0.3;
+const MIE_ANISOTROPY: f32 = 0.76;
+// Lleva la luz dispersada al rango del color de las superficies
+const EXPOSURE: f32 = 10.0;
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AtmosphereDescription {
+    // Coeficientes de Rayleigh relativos: los canales altos se dispersan más (cielo) y
+    // se pierden antes en los caminos largos (atardecer del color complementario)
+    #[serde(default = "default_color")]
+    pub color: [f32; 3],
+    #[serde(default = "default_density")]
+    pub density: f32,
+    // Espesor de la capa en radios del cuerpo
+    #[serde(default = "default_height")]
+    pub height: f32,
+}
+
+fn default_color() -> [f32; 3] {
+    [0.25, 0.5, 1.0]
+}
+
+fn default_density() -> f32 {
+    1.0
+}
+
+fn default_height() -> f32 {
+    0.08
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Atmosphere {
+    pub color: Vec3,
+    pub density: f32,
+    pub height: f32,
+}
+
+impl Atmosphere {
+    pub fn new(description: &AtmosphereDescription) -> Result<Self, String> {
+        if description.density < 0.0 || description.color.iter().any(|&channel| channel < 0.0) {
+            return Err("el color y la densidad de la atmósfera no pueden ser negativos".to_string());
+        }
+        if description.height <= 0.0 {
+            return Err("la altura de la atmósfera debe ser positiva".to_string());
+        }
+        Ok(Atmosphere {
+            color: Vec3::from(description.color),
+            density: description.density,
+            height: description.height,
+        })
+    }
+
+    // Escala de la esfera que envuelve a un cuerpo de escala `scale`
+    pub fn shell_scale(&self, scale: f32) -> f32 {
+        scale * (1.0 + self.height)
+    }
+
+    // Luz que la capa (centro `center`, radio exterior `radius`) dispersa hacia `eye` a lo
+    // largo del rayo que pasa por `point`. `occluders` no incluye al propio cuerpo
+    pub fn in_scattering(&self, center: &Vec3, radius: f32, eye: &Vec3, point: &Vec3, lights: &[Light], occluders: &[Occluder]) -> Vec3 {
+        let planet_radius = radius / (1.0 + self.height);
+        let thickness = radius - planet_radius;
+        let view_dir = (point - eye).normalize();
+
+        let Some((near, far)) = ray_sphere(eye, &view_dir, center, radius) else {
+            return Vec3::zeros();
+        };
+        // El rayo empieza en la cámara si está dentro de la capa y termina en el suelo
+        let near = near.max(0.0);
+        let far = match ray_sphere(eye, &view_dir, center, planet_radius) {
+            Some((ground, _)) if ground > 0.0 => far.min(ground),
+            _ => far,
+        };
+        if far <= near {
+            return Vec3::zeros();
+        }
+
+        let rayleigh = self.color * self.density;
+        let mie = self.density * MIE_STRENGTH;
+        let step = (far - near) / VIEW_SAMPLES as f32;
+        let step_depth = step / thickness;
+
+        let middle = eye + view_dir * ((near + far) * 0.5);
+
+        let mut scattered = Vec3::zeros();
+        for light in lights {
+            // Los eclipses de los demás cuerpos cambian poco a lo largo de la capa: se
+            // evalúan una vez por rayo, en su punto medio
+            let eclipse = light.visibility(&middle, &light.incident(&middle).0, occluders);
+            if eclipse <= 0.0 {
+                continue;
+            }
+
+            let mut view_depth = (0.0, 0.0);
+            for i in 0..VIEW_SAMPLES {
+                let sample = eye + view_dir * (near + (i as f32 + 0.5) * step);
+                let (rayleigh_density, mie_density) = self.densities(center, planet_radius, thickness, &sample);
+                view_depth.0 += rayleigh_density * step_depth;
+                view_depth.1 += mie_density * step_depth;
+
+                let (light_dir, radiance) = light.incident(&sample);
+                // El propio cuerpo tapa la luz: la capa del lado nocturno no brilla
+                if ray_sphere(&sample, &light_dir, center, planet_radius).is_some_and(|(hit, _)| hit > 0.0) {
+                    continue;
+                }
+
+                let light_depth = self.light_depth(center, radius, planet_radius, thickness, &sample, &light_dir);
+                let optical_depth = rayleigh * (view_depth.0 + light_depth.0) + Vec3::repeat(mie * 1.1 * (view_depth.1 + light_depth.1));
+                let transmittance = optical_depth.map(|depth| (-depth).exp());
+
+                let cos_angle = view_dir.dot(&light_dir);
+                let phase = rayleigh * (rayleigh_density * rayleigh_phase(cos_angle))
+                    + Vec3::repeat(mie * mie_density * mie_phase(cos_angle));
+                scattered += transmittance.component_mul(&phase).component_mul(&radiance) * (eclipse * step_depth);
+            }
+        }
+        scattered * EXPOSURE
+    }
+
+    // Densidad relativa de Rayleigh y Mie a la altura de `point` (1 en el suelo)
+    fn densities(&self, center: &Vec3, planet_radius: f32, thickness: f32, point: &Vec3) -> (f32, f32) {
+        let altitude = (((point - center).magnitude() - planet_radius) / thickness).max(0.0);
+        ((-altitude / RAYLEIGH_SCALE_HEIGHT).exp(), (-altitude / MIE_SCALE_HEIGHT).exp())
+    }
+
+    // Profundidad óptica desde `point` hasta salir de la capa en dirección a la luz
+    fn light_depth(&self, center: &Vec3, radius: f32, planet_radius: f32, thickness: f32, point: &Vec3, light_dir: &Vec3) -> (f32, f32) {
+        let exit = ray_sphere(point, light_dir, center, radius).map_or(0.0, |(_, far)| far.max(0.0));
+        let step = exit / LIGHT_SAMPLES as f32;
+        let mut depth = (0.0, 0.0);
+        for i in 0..LIGHT_SAMPLES {
+            let sample = point + light_dir * ((i as f32 + 0.5) * step);
+            let (rayleigh_density, mie_density) = self.densities(center, planet_radius, thickness, &sample);
+            depth.0 += rayleigh_density * step / thickness;
+            depth.1 += mie_density * step / thickness;
+        }
+        depth
+    }
+}
+
+// Distancias de entrada y salida de un rayo (dirección normalizada) en una esfera
+fn ray_sphere(origin: &Vec3, dir: &Vec3, center: &Vec3, radius: f32) -> Option<(f32, f32)> {
+    let offset = origin - center;
+    let b = offset.dot(dir);
+    let c = offset.magnitude_squared() - radius * radius;
+    let discriminant = b * b - c;
+    if discriminant < 0.0 {
+        return None;
+    }
+    let root = discriminant.sqrt();
+    Some((-b - root, -b + root))
+}
+
+fn rayleigh_phase(cos_angle: f32) -> f32 {
+    3.0 / (16.0 * PI) * (1.0 + cos_angle * cos_angle)
+}
+
+// Henyey-Greenstein
+fn mie_phase(cos_angle: f32) -> f32 {
+    let g = MIE_ANISOTROPY;
+    let denominator = (1.0 + g * g - 2.0 * g * cos_angle).max(1e-4);
+    (1.0 - g * g) / (4.0 * PI * denominator * denominator.sqrt())
+}
@@ -0,0 +1,231 @@
+// bloom.rs - Resplandor de lo más brillante de la imagen (Sol, lava, motores de la nave)
+//
+// Trabaja sobre el framebuffer HDR antes del tone mapping. La luz que pasa del umbral se
+// copia a media resolución y se reduce a la mitad `radius` veces, suavizando en cada paso;
+// luego se vuelve a subir nivel por nivel sumando lo de cada uno. Cada nivel duplica el
+// alcance del halo, así que el resplandor cae suave lejos de la silueta sin un blur enorme.
+
+use nalgebra_glm::Vec3;
+use serde::Deserialize;
+use crate::color::Color;
+use crate::framebuffer::Framebuffer;
+
+// Ancho de la transición alrededor del umbral, en fracción del umbral: evita que los
+// píxeles que rozan el umbral parpadeen al entrar y salir del bloom
+const KNEE: f32 = 0.5;
+// Pesos del filtro tienda de 4 taps por eje al reducir a la mitad
+const TENT: [f32; 4] = [1.0 / 8.0, 3.0 / 8.0, 3.0 / 8.0, 1.0 / 8.0];
+pub const MAX_RADIUS: u32 = 8;
+
+// Sección [bloom] del archivo de escena
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BloomSettings {
+    pub enabled: bool,
+    // Luminancia lineal a partir de la que un píxel resplandece (1.0 = blanco de referencia)
+    pub threshold: f32,
+    // Cuánto del resplandor se suma a la imagen
+    pub intensity: f32,
+    // Niveles de la cadena de reducción; cada uno duplica el alcance del halo
+    pub radius: u32,
+}
+
+impl Default for BloomSettings {
+    fn default() -> Self {
+        BloomSettings {
+            enabled: true,
+            threshold: 0.9,
+            intensity: 0.6,
+            radius: 5,
+        }
+    }
+}
+
+impl BloomSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.threshold < 0.0 || self.intensity < 0.0 {
+            return Err("bloom.threshold y bloom.intensity no pueden ser negativos".to_string());
+        }
+        if !(1..=MAX_RADIUS).contains(&self.radius) {
+            return Err(format!("bloom.radius debe estar entre 1 y {}", MAX_RADIUS));
+        }
+        Ok(())
+    }
+}
+
+// Un nivel de la cadena, en RGB lineal
+struct Level {
+    width: usize,
+    height: usize,
+    pixels: Vec<Vec3>,
+}
+
+impl Level {
+    fn new(width: usize, height: usize) -> Self {
+        Level { width, height, pixels: vec![Vec3::zeros(); width * height] }
+    }
+
+    fn at(&self, x: isize, y: isize) -> Vec3 {
+        let x = x.clamp(0, self.width as isize - 1) as usize;
+        let y = y.clamp(0, self.height as isize - 1) as usize;
+        self.pixels[y * self.width + x]
+    }
+
+    // Muestreo bilineal con (u, v) en [0, 1]
+    fn sample(&self, u: f32, v: f32) -> Vec3 {
+        let x = u * self.width as f32 - 0.5;
+        let y = v * self.height as f32 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as isize, y0 as isize);
+        let top = self.at(x0, y0) * (1.0 - fx) + self.at(x0 + 1, y0) * fx;
+        let bottom = self.at(x0, y0 + 1) * (1.0 - fx) + self.at(x0 + 1, y0 + 1) * fx;
+        top * (1.0 - fy) + bottom * fy
+    }
+}
+
+// Los niveles se guardan entre frames para no reservar memoria en cada uno
+#[derive(Default)]
+pub struct Bloom {
+    levels: Vec<Level>,
+}
+
+impl Bloom {
+    pub fn apply(&mut self, settings: &BloomSettings, framebuffer: &mut Framebuffer) {
+        if !settings.enabled || settings.intensity <= 0.0 {
+            return;
+        }
+        self.resize(framebuffer.width, framebuffer.height, settings.radius as usize);
+
+        // Filtro de brillo a media resolución
+        let source = |x: isize, y: isize| {
+            let x = x.clamp(0, framebuffer.width as isize - 1) as usize;
+            let y = y.clamp(0, framebuffer.height as isize - 1) as usize;
+            framebuffer.hdr[y * framebuffer.width + x].rgb()
+        };
+        let first = &mut self.levels[0];
+        for y in 0..first.height {
+            for x in 0..first.width {
+                let color = downsample(&source, x, y);
+                first.pixels[y * first.width + x] = bright_part(&color, settings.threshold);
+            }
+        }
+
+        // Cadena de reducción
+        for index in 1..self.levels.len() {
+            let (done, rest) = self.levels.split_at_mut(index);
+            let (previous, level) = (&done[index - 1], &mut rest[0]);
+            let source = |x: isize, y: isize| previous.at(x, y);
+            for y in 0..level.height {
+                for x in 0..level.width {
+                    level.pixels[y * level.width + x] = downsample(&source, x, y);
+                }
+            }
+        }
+
+        // Subida: cada nivel suma el de abajo ampliado, así el más fino acumula todos
+        for index in (0..self.levels.len() - 1).rev() {
+            let (head, tail) = self.levels.split_at_mut(index + 1);
+            let (level, coarser) = (&mut head[index], &tail[0]);
+            for y in 0..level.height {
+                let v = (y as f32 + 0.5) / level.height as f32;
+                for x in 0..level.width {
+                    let u = (x as f32 + 0.5) / level.width as f32;
+                    level.pixels[y * level.width + x] += coarser.sample(u, v);
+                }
+            }
+        }
+
+        // Se normaliza por el número de niveles para que `radius` no cambie el brillo total
+        let scale = settings.intensity / self.levels.len() as f32;
+        let glow = &self.levels[0];
+        for y in 0..framebuffer.height {
+            let v = (y as f32 + 0.5) / framebuffer.height as f32;
+            for x in 0..framebuffer.width {
+                let u = (x as f32 + 0.5) / framebuffer.width as f32;
+                let light = glow.sample(u, v) * scale;
+                let pixel = &mut framebuffer.hdr[y * framebuffer.width + x];
+                *pixel = *pixel + Color::from_float(light.x, light.y, light.z);
+            }
+        }
+    }
+
+    fn resize(&mut self, width: usize, height: usize, radius: usize) {
+        let mut sizes = Vec::with_capacity(radius);
+        let (mut w, mut h) = (width, height);
+        for _ in 0..radius {
+            w = w.div_ceil(2);
+            h = h.div_ceil(2);
+            sizes.push((w, h));
+        }
+        let matches = self.levels.len() == sizes.len()
+            && self.levels.iter().zip(&sizes).all(|(level, &(w, h))| level.width == w && level.height == h);
+        if !matches {
+            self.levels = sizes.into_iter().map(|(w, h)| Level::new(w, h)).collect();
+        }
+    }
+}
+
+// Píxel (x, y) de un nivel a mitad de resolución que `source`: filtro tienda de 4x4
+fn downsample(source: &impl Fn(isize, isize) -> Vec3, x: usize, y: usize) -> Vec3 {
+    let (x0, y0) = (2 * x as isize - 1, 2 * y as isize - 1);
+    let mut sum = Vec3::zeros();
+    for (j, wy) in TENT.iter().enumerate() {
+        for (i, wx) in TENT.iter().enumerate() {
+            sum += source(x0 + i as isize, y0 + j as isize) * (wx * wy);
+        }
+    }
+    sum
+}
+
+// Parte de la luz de `color` que pasa del umbral, con una rodilla cuadrática
+fn bright_part(color: &Vec3, threshold: f32) -> Vec3 {
+    let luminance = Color::from_float(color.x, color.y, color.z).luminance();
+    if luminance <= 0.0 {
+        return Vec3::zeros();
+    }
+    let knee = threshold * KNEE + 1e-4;
+    let soft = (luminance - threshold + knee).clamp(0.0, 2.0 * knee);
+    let soft = soft * soft / (4.0 * knee);
+    color * ((luminance - threshold).max(soft) / luminance)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bright_luminance(luminance: f32, threshold: f32) -> f32 {
+        bright_part(&Vec3::repeat(luminance), threshold).x
+    }
+
+    #[test]
+    fn below_the_knee_nothing_blooms() {
+        let threshold = 1.0;
+        for luminance in [0.0, 0.1, 0.4, threshold * (1.0 - KNEE) - 1e-3] {
+            assert_eq!(bright_luminance(luminance, threshold), 0.0, "{}", luminance);
+        }
+    }
+
+    #[test]
+    fn above_the_knee_only_the_excess_blooms() {
+        let threshold = 1.0;
+        for luminance in [1.6, 2.0, 10.0] {
+            assert!((bright_luminance(luminance, threshold) - (luminance - threshold)).abs() < 1e-4, "{}", luminance);
+        }
+        // El color conserva su tono
+        let bright = bright_part(&Vec3::new(4.0, 2.0, 1.0), threshold);
+        assert!((bright.x / bright.y - 2.0).abs() < 1e-4 && (bright.y / bright.z - 2.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn knee_is_smooth_and_monotone() {
+        let threshold = 1.0;
+        let samples: Vec<f32> = (0..=300).map(|i| bright_luminance(i as f32 * 0.01, threshold)).collect();
+        assert!(samples.windows(2).all(|pair| pair[1] >= pair[0]));
+        // Sin saltos al entrar y salir de la rodilla
+        assert!(samples.windows(2).all(|pair| pair[1] - pair[0] < 0.011));
+        // Dentro de la rodilla ya hay algo de resplandor, menos que el exceso lineal
+        let at_threshold = bright_luminance(threshold, threshold);
+        assert!(at_threshold > 0.0 && at_threshold < 0.5 * KNEE);
+    }
+}
@@ -12,6 +12,13 @@ pub struct Camera {
     pub speed: f32,
     pub sensitivity: f32,
     pub zoom: f32,
+    // Deriva de la nave bajo la gravedad (modo N cuerpos)
+    pub velocity: Vec3,
+
+    // Proyección en perspectiva
+    pub fov: f32,    // Campo de visión vertical en grados
+    pub near: f32,
+    pub far: f32,
     
     // Para warping
     pub is_warping: bool,
@@ -32,6 +39,10 @@ impl Camera {
             speed: 15.0,
             sensitivity: 0.1,
             zoom: 1.0,
+            velocity: Vec3::zeros(),
+            fov: 60.0,
+            near: 1.0,
+            far: 10000.0,
             is_warping: false,
             warp_progress: 0.0,
             warp_start: Vec3::zeros(),
@@ -97,11 +108,20 @@ impl Camera {
         self.update_vectors();
     }
 
+    // Orienta la cámara hacia un punto del mundo (actualiza yaw/pitch)
+    pub fn look_at(&mut self, point: Vec3) {
+        let direction = (point - self.position).normalize();
+        self.yaw = direction.z.atan2(direction.x).to_degrees();
+        self.pitch = direction.y.asin().to_degrees().clamp(-89.0, 89.0);
+        self.update_vectors();
+    }
+
     pub fn start_warp(&mut self, target: Vec3) {
         self.is_warping = true;
         self.warp_progress = 0.0;
         self.warp_start = self.position;
         self.warp_end = target;
+        self.velocity = Vec3::zeros();
     }
 
     pub fn update_warp(&mut self, delta_time: f32) -> bool {
@@ -138,36 +158,8 @@ impl Camera {
         )
     }
 
-    pub fn get_screen_position(&self, world_pos: Vec3, window_width: f32, window_height: f32) -> Vec3 {
-        // Proyección simple 3D a 2D
-        let relative_pos = world_pos - self.position;
-        let distance = relative_pos.magnitude();
-
-        if distance < 0.1 {
-            return Vec3::new(window_width / 2.0, window_height / 2.0, 0.0);
-        }
-
-        // Proyección en pantalla
-        let forward = self.target;
-        let right = forward.cross(&self.up).normalize();
-        let up = right.cross(&forward).normalize();
-
-        let local_x = relative_pos.dot(&right);
-        let local_y = relative_pos.dot(&up);
-        let local_z = relative_pos.dot(&forward);
-
-        if local_z <= 0.1 {
-            // Objeto detrás de la cámara
-            return Vec3::new(-1000.0, -1000.0, -1.0);
-        }
-
-        let fov = 60.0_f32.to_radians();
-        let aspect = window_width / window_height;
-
-        let screen_x = window_width / 2.0 + (local_x / local_z) * (window_width / (2.0 * (fov / 2.0).tan()));
-        let screen_y = window_height / 2.0 - (local_y / local_z) * (window_height / (2.0 * (fov / 2.0).tan() / aspect));
-
-        Vec3::new(screen_x, screen_y, local_z)
+    pub fn get_projection_matrix(&self, aspect: f32) -> Mat4 {
+        nalgebra_glm::perspective(aspect, self.fov.to_radians(), self.near, self.far)
     }
 
     pub fn check_collision(&self, object_pos: Vec3, object_radius: f32) -> bool {
@@ -179,6 +171,19 @@ impl Camera {
         let direction = (self.position - object_pos).normalize();
         let min_distance = object_radius + 50.0;
         self.position = object_pos + direction * min_distance;
+
+        // Anula la velocidad hacia el cuerpo para quedar apoyado en vez de atravesarlo
+        let inward = self.velocity.dot(&direction).min(0.0);
+        self.velocity -= direction * inward;
+    }
+
+    // Integra la deriva de la nave con una aceleración externa (Euler semi-implícito)
+    pub fn apply_gravity(&mut self, acceleration: Vec3, delta_time: f32) {
+        if self.is_warping {
+            return;
+        }
+        self.velocity += acceleration * delta_time;
+        self.position += self.velocity * delta_time;
     }
 }
 
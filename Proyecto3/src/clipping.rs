@@ -0,0 +1,201 @@
+// clipping.rs - Recorte de triángulos contra el frustum en clip space (Sutherland–Hodgman)
+
+use nalgebra_glm::{Vec3, Vec4, Mat4};
+use crate::vertex::Vertex;
+
+// Planos del frustum en clip space: un punto está dentro si la distancia es >= 0
+#[derive(Clone, Copy)]
+enum ClipPlane {
+    Left,
+    Right,
+    Bottom,
+    Top,
+    Near,
+    Far,
+}
+
+const CLIP_PLANES: [ClipPlane; 6] = [
+    ClipPlane::Near,
+    ClipPlane::Far,
+    ClipPlane::Left,
+    ClipPlane::Right,
+    ClipPlane::Bottom,
+    ClipPlane::Top,
+];
+
+impl ClipPlane {
+    fn distance(&self, p: &Vec4) -> f32 {
+        match self {
+            ClipPlane::Left => p.w + p.x,
+            ClipPlane::Right => p.w - p.x,
+            ClipPlane::Bottom => p.w + p.y,
+            ClipPlane::Top => p.w - p.y,
+            ClipPlane::Near => p.w + p.z,
+            ClipPlane::Far => p.w - p.z,
+        }
+    }
+}
+
+/// Recorta un triángulo ya procesado por el vertex shader contra el frustum.
+/// Devuelve los triángulos resultantes (en abanico) con posiciones de pantalla recalculadas.
+pub fn clip_triangle(v1: &Vertex, v2: &Vertex, v3: &Vertex, viewport_matrix: &Mat4) -> Vec<[Vertex; 3]> {
+    let vertices = [v1, v2, v3];
+
+    // Caso rápido: completamente fuera de algún plano
+    for plane in CLIP_PLANES.iter() {
+        if vertices.iter().all(|v| plane.distance(&v.clip_position) < 0.0) {
+            return Vec::new();
+        }
+    }
+
+    // Caso rápido: completamente dentro, no hace falta recortar
+    let inside = vertices.iter().all(|v| {
+        CLIP_PLANES.iter().all(|plane| plane.distance(&v.clip_position) >= 0.0)
+    });
+    if inside {
+        return vec![[v1.clone(), v2.clone(), v3.clone()]];
+    }
+
+    let mut polygon: Vec<Vertex> = vec![v1.clone(), v2.clone(), v3.clone()];
+
+    for plane in CLIP_PLANES.iter() {
+        if polygon.is_empty() {
+            break;
+        }
+
+        let mut output = Vec::with_capacity(polygon.len() + 1);
+        for i in 0..polygon.len() {
+            let current = &polygon[i];
+            let next = &polygon[(i + 1) % polygon.len()];
+            let d_current = plane.distance(&current.clip_position);
+            let d_next = plane.distance(&next.clip_position);
+
+            if d_current >= 0.0 {
+                output.push(current.clone());
+            }
+            // La arista cruza el plano: agrega el punto de intersección
+            if (d_current >= 0.0) != (d_next >= 0.0) {
+                let t = d_current / (d_current - d_next);
+                output.push(interpolate_vertex(current, next, t));
+            }
+        }
+        polygon = output;
+    }
+
+    if polygon.len() < 3 {
+        return Vec::new();
+    }
+
+    for vertex in polygon.iter_mut() {
+        vertex.transformed_position = to_screen(&vertex.clip_position, viewport_matrix);
+    }
+
+    // Triangulación en abanico del polígono convexo resultante
+    (1..polygon.len() - 1)
+        .map(|i| [polygon[0].clone(), polygon[i].clone(), polygon[i + 1].clone()])
+        .collect()
+}
+
+/// Recorta un segmento en clip space contra el frustum (Liang–Barsky sobre los mismos planos).
+/// Devuelve los extremos del tramo visible, o None si queda entero fuera.
+pub fn clip_line(a: Vec4, b: Vec4) -> Option<(Vec4, Vec4)> {
+    let (mut t_start, mut t_end) = (0.0_f32, 1.0_f32);
+    for plane in CLIP_PLANES.iter() {
+        let d_start = plane.distance(&a);
+        let d_end = plane.distance(&b);
+        if d_start < 0.0 && d_end < 0.0 {
+            return None;
+        }
+        // Fracción del segmento en la que cruza el plano
+        if d_start < 0.0 {
+            t_start = t_start.max(d_start / (d_start - d_end));
+        } else if d_end < 0.0 {
+            t_end = t_end.min(d_start / (d_start - d_end));
+        }
+    }
+    (t_start < t_end).then(|| (a + (b - a) * t_start, a + (b - a) * t_end))
+}
+
+fn interpolate_vertex(a: &Vertex, b: &Vertex, t: f32) -> Vertex {
+    Vertex {
+        position: a.position + (b.position - a.position) * t,
+        normal: a.normal + (b.normal - a.normal) * t,
+        tangent: a.tangent + (b.tangent - a.tangent) * t,
+        tex_coords: a.tex_coords + (b.tex_coords - a.tex_coords) * t,
+        color: a.color.lerp(&b.color, t),
+        world_position: a.world_position + (b.world_position - a.world_position) * t,
+        clip_position: a.clip_position + (b.clip_position - a.clip_position) * t,
+        transformed_position: a.transformed_position,
+        transformed_normal: a.transformed_normal + (b.transformed_normal - a.transformed_normal) * t,
+    }
+}
+
+// Perspective division + viewport para un vértice generado por el recorte
+pub fn to_screen(clip_position: &Vec4, viewport_matrix: &Mat4) -> Vec3 {
+    let w = clip_position.w;
+    let ndc = Vec4::new(clip_position.x / w, clip_position.y / w, clip_position.z / w, 1.0);
+    let screen = viewport_matrix * ndc;
+    Vec3::new(screen.x, screen.y, screen.z)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inside_frustum(p: &Vec4) -> bool {
+        CLIP_PLANES.iter().all(|plane| plane.distance(p) >= -1e-5)
+    }
+
+    #[test]
+    fn segment_inside_is_unchanged() {
+        let (a, b) = (Vec4::new(-0.5, 0.2, 0.1, 1.0), Vec4::new(0.5, -0.2, 0.3, 2.0));
+        assert_eq!(clip_line(a, b), Some((a, b)));
+    }
+
+    #[test]
+    fn segment_outside_is_dropped() {
+        // Los dos extremos detrás del plano cercano, o los dos a la izquierda
+        assert_eq!(clip_line(Vec4::new(0.0, 0.0, -3.0, 1.0), Vec4::new(0.5, 0.0, -2.0, 1.0)), None);
+        assert_eq!(clip_line(Vec4::new(-3.0, 0.0, 0.0, 1.0), Vec4::new(-2.0, 1.0, 0.0, 1.0)), None);
+    }
+
+    #[test]
+    fn segment_straddling_near_plane_is_cut_at_it() {
+        let (a, b) = (Vec4::new(0.0, 0.0, -3.0, 1.0), Vec4::new(0.0, 0.0, 0.0, 1.0));
+        let (start, end) = clip_line(a, b).unwrap();
+        assert!(ClipPlane::Near.distance(&start).abs() < 1e-6);
+        assert_eq!(end, b);
+        assert!(inside_frustum(&start) && inside_frustum(&end));
+    }
+
+    fn clip_vertex(clip_position: Vec4) -> Vertex {
+        Vertex { clip_position, ..Vertex::default() }
+    }
+
+    #[test]
+    fn triangle_straddling_near_plane_becomes_a_quad() {
+        let triangle = [
+            clip_vertex(Vec4::new(-0.5, -0.5, 0.5, 1.0)),
+            clip_vertex(Vec4::new(0.5, -0.5, 0.5, 1.0)),
+            clip_vertex(Vec4::new(0.0, 0.5, -2.0, 1.0)),
+        ];
+        let clipped = clip_triangle(&triangle[0], &triangle[1], &triangle[2], &Mat4::identity());
+        assert_eq!(clipped.len(), 2);
+        for vertex in clipped.iter().flatten() {
+            assert!(inside_frustum(&vertex.clip_position));
+            // La posición de pantalla se recalcula para los vértices nuevos
+            let w = vertex.clip_position.w;
+            assert!((vertex.transformed_position.z - vertex.clip_position.z / w).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn triangle_behind_near_plane_is_dropped() {
+        let triangle = [
+            clip_vertex(Vec4::new(-0.5, -0.5, -2.0, 1.0)),
+            clip_vertex(Vec4::new(0.5, -0.5, -3.0, 1.0)),
+            clip_vertex(Vec4::new(0.0, 0.5, -2.5, 1.0)),
+        ];
+        assert!(clip_triangle(&triangle[0], &triangle[1], &triangle[2], &Mat4::identity()).is_empty());
+    }
+}
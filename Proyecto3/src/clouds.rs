@@ -0,0 +1,157 @@
+// clouds.rs - Capa de nubes: una esfera translúcida un poco por encima de la superficie
+//
+// La cobertura se calcula con ruido sobre la dirección desde el centro del cuerpo, así que
+// la misma función sirve para dibujar la capa y para la sombra que proyecta sobre el
+// suelo. La capa gira alrededor del eje Y a su propio ritmo, independiente del cuerpo.
+
+use nalgebra_glm::Vec3;
+use serde::Deserialize;
+use crate::shader::{cloud_noise, smoothstep};
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CloudsDescription {
+    // Fracción aproximada del cielo cubierta (0 = despejado, 1 = cubierto)
+    pub coverage: f32,
+    // Altura de la capa en radios del cuerpo
+    pub height: f32,
+    // Rad/s, alrededor del eje Y
+    pub rotation_speed: f32,
+    // Frecuencia del ruido, octavas y desplazamiento dentro del campo de ruido
+    pub scale: f32,
+    pub octaves: u32,
+    pub seed: f32,
+    // Opacidad de las nubes más densas
+    pub opacity: f32,
+    // Cuánto oscurecen el suelo que tapan de la luz (0 = sin sombra)
+    pub shadow: f32,
+}
+
+impl Default for CloudsDescription {
+    fn default() -> Self {
+        CloudsDescription {
+            coverage: 0.4,
+            height: 0.02,
+            rotation_speed: 0.03,
+            scale: 2.5,
+            octaves: 4,
+            seed: 0.0,
+            opacity: 0.85,
+            shadow: 0.5,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct CloudLayer {
+    pub coverage: f32,
+    pub height: f32,
+    pub rotation_speed: f32,
+    pub scale: f32,
+    pub octaves: u32,
+    pub seed: f32,
+    pub opacity: f32,
+    pub shadow: f32,
+    // Ángulo actual de la capa (avanza con `rotation_speed`)
+    pub rotation: f32,
+}
+
+impl CloudLayer {
+    pub fn new(description: &CloudsDescription) -> Result<Self, String> {
+        if !(0.0..=1.0).contains(&description.coverage) {
+            return Err("clouds.coverage debe estar en [0, 1]".to_string());
+        }
+        if !(0.0..=1.0).contains(&description.opacity) || !(0.0..=1.0).contains(&description.shadow) {
+            return Err("clouds.opacity y clouds.shadow deben estar en [0, 1]".to_string());
+        }
+        if description.height <= 0.0 || description.scale <= 0.0 || description.octaves == 0 {
+            return Err("clouds necesita height y scale positivos y al menos una octava".to_string());
+        }
+        Ok(CloudLayer {
+            coverage: description.coverage,
+            height: description.height,
+            rotation_speed: description.rotation_speed,
+            scale: description.scale,
+            octaves: description.octaves,
+            seed: description.seed,
+            opacity: description.opacity,
+            shadow: description.shadow,
+            rotation: 0.0,
+        })
+    }
+
+    // Escala de la esfera de nubes de un cuerpo de escala `scale`
+    pub fn shell_scale(&self, scale: f32) -> f32 {
+        scale * (1.0 + self.height)
+    }
+
+    // Densidad de las nubes (0 a 1) en la dirección `direction` desde el centro del cuerpo,
+    // en ejes de mundo
+    pub fn density(&self, direction: &Vec3) -> f32 {
+        let (sin, cos) = self.rotation.sin_cos();
+        let direction = direction.normalize();
+        // Dirección en el marco de la capa (girada -rotation alrededor de Y)
+        let local = Vec3::new(direction.x * cos - direction.z * sin, direction.y, direction.x * sin + direction.z * cos);
+        let noise = cloud_noise(&(local * self.scale + Vec3::repeat(self.seed)), &local, self.octaves);
+        // El ruido se concentra alrededor de 0.46 (entre 0.27 y 0.61 en el 80% de la
+        // esfera): umbral aproximado para que `coverage` sea la fracción cubierta
+        let threshold = 0.46 + (0.5 - self.coverage) * 0.45;
+        smoothstep(threshold - 0.03, threshold + 0.03, noise)
+    }
+
+    // Fracción de luz que llega a `point` (en la superficie) tras cruzar la capa hacia la luz
+    pub fn transmittance(&self, center: &Vec3, radius: f32, point: &Vec3, light_dir: &Vec3) -> f32 {
+        if self.shadow <= 0.0 {
+            return 1.0;
+        }
+        // Donde el rayo hacia la luz sale de la esfera de nubes
+        let shell_radius = radius * (1.0 + self.height);
+        let offset = point - center;
+        let b = offset.dot(light_dir);
+        let c = offset.magnitude_squared() - shell_radius * shell_radius;
+        let discriminant = b * b - c;
+        // El rayo no toca la esfera, o la deja atrás: ninguna nube se interpone
+        if discriminant < 0.0 {
+            return 1.0;
+        }
+        let exit = -b + discriminant.sqrt();
+        if exit < 0.0 {
+            return 1.0;
+        }
+        let crossing = offset + light_dir * exit;
+        1.0 - self.shadow * self.opacity * self.density(&crossing)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overcast() -> CloudLayer {
+        CloudLayer::new(&CloudsDescription { coverage: 1.0, opacity: 1.0, shadow: 1.0, ..Default::default() }).unwrap()
+    }
+
+    #[test]
+    fn surface_takes_the_density_where_the_ray_leaves_the_shell() {
+        let clouds = overcast();
+        let light_dir = Vec3::new(0.0, 1.0, 0.0);
+        let point = Vec3::new(1.0, 0.0, 0.0);
+        // Punto en el ecuador con la luz sobre el polo: el rayo cruza la capa en diagonal
+        let transmittance = clouds.transmittance(&Vec3::zeros(), 1.0, &point, &light_dir);
+        let exit = ((1.0 + clouds.height).powi(2) - 1.0).sqrt();
+        let expected = 1.0 - clouds.density(&(point + light_dir * exit));
+        assert!((transmittance - expected).abs() < 1e-5, "{} != {}", transmittance, expected);
+    }
+
+    #[test]
+    fn point_outside_the_shell_is_not_shadowed() {
+        let clouds = overcast();
+        let center = Vec3::zeros();
+        // El rayo hacia la luz pasa de largo junto a la esfera
+        let beside = clouds.transmittance(&center, 1.0, &Vec3::new(0.0, 3.0, 0.0), &Vec3::new(1.0, 0.0, 0.0));
+        assert_eq!(beside, 1.0);
+        // La esfera queda detrás del punto, del lado contrario a la luz
+        let behind = clouds.transmittance(&center, 1.0, &Vec3::new(3.0, 0.0, 0.0), &Vec3::new(1.0, 0.0, 0.0));
+        assert_eq!(behind, 1.0);
+    }
+}
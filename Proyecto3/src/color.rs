@@ -1,43 +1,150 @@
 use std::fmt;
+use std::sync::LazyLock;
+use nalgebra_glm::Vec3;
+use serde::Deserialize;
 
+// Color en RGB lineal. Los canales no se saturan: 1.0 es el blanco de referencia y las
+// superficies emisivas pueden pasarse; el framebuffer HDR los acumula tal cual y el tone
+// mapping los lleva a la pantalla
 #[derive(Debug, Clone, Copy)]
 pub struct Color {
-  r: u8,
-  g: u8,
-  b: u8,
+  r: f32,
+  g: f32,
+  b: f32,
+  a: f32, // Opacidad en [0, 1] (1 = opaco); el framebuffer solo guarda RGB
+}
+
+// Cómo se combina un fragmento transparente con el color que ya hay en el framebuffer
+#[derive(Clone, Copy, PartialEq, Debug, Deserialize)]
+pub enum BlendMode {
+  // Mezcla según la opacidad del fragmento
+  Alpha,
+  // Suma la luz del fragmento (brillos, coronas, partículas)
+  Additive,
+  // Tiñe lo que hay detrás con el color del fragmento
+  Multiply,
+}
+
+// Los colores de 8 bits (paletas de los shaders, hex, texturas) están en sRGB
+static SRGB_TO_LINEAR: LazyLock<[f32; 256]> = LazyLock::new(|| std::array::from_fn(|i| srgb_to_linear(i as f32 / 255.0)));
+
+// Codificación de vuelta a 8 bits, tabulada con suficiente resolución para las sombras
+const ENCODE_STEPS: usize = 4096;
+static LINEAR_TO_SRGB: LazyLock<Vec<u8>> = LazyLock::new(|| {
+  (0..=ENCODE_STEPS)
+    .map(|i| (linear_to_srgb(i as f32 / ENCODE_STEPS as f32) * 255.0).round() as u8)
+    .collect()
+});
+
+pub fn srgb_to_linear(value: f32) -> f32 {
+  if value <= 0.04045 {
+    value / 12.92
+  } else {
+    ((value + 0.055) / 1.055).powf(2.4)
+  }
+}
+
+fn linear_to_srgb(value: f32) -> f32 {
+  if value <= 0.0031308 {
+    value * 12.92
+  } else {
+    1.055 * value.powf(1.0 / 2.4) - 0.055
+  }
+}
+
+fn decode(channel: u8) -> f32 {
+  SRGB_TO_LINEAR[channel as usize]
+}
+
+fn encode(channel: f32) -> u8 {
+  LINEAR_TO_SRGB[(channel.clamp(0.0, 1.0) * ENCODE_STEPS as f32).round() as usize]
 }
 
 impl Color {
-  // Constructor to initialize the color using r, g, b values as u8
+  // Constructor to initialize the color using r, g, b values as u8 (sRGB)
   pub fn new(r: u8, g: u8, b: u8) -> Self {
-    Color { r, g, b }
+    Color { r: decode(r), g: decode(g), b: decode(b), a: 1.0 }
+  }
+
+  pub fn new_rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
+    Color { a: a as f32 / 255.0, ..Color::new(r, g, b) }
   }
 
   // default color
   pub fn black() -> Self {
-    Color { r: 0, g: 0, b: 0 }
+    Color { r: 0.0, g: 0.0, b: 0.0, a: 1.0 }
   }
 
-  // New constructor to initialize the color using r, g, b values as f32 (0.0 to 1.0)
+  // New constructor to initialize the color using linear r, g, b values as f32 (1.0 = blanco)
   pub fn from_float(r: f32, g: f32, b: f32) -> Self {
+    Color { r: r.max(0.0), g: g.max(0.0), b: b.max(0.0), a: 1.0 }
+  }
+
+  // Function to create a color from a hex value (sRGB)
+  pub fn from_hex(hex: u32) -> Self {
+    Color::new(((hex >> 16) & 0xFF) as u8, ((hex >> 8) & 0xFF) as u8, (hex & 0xFF) as u8)
+  }
+
+  // Canales lineales, sin la opacidad
+  pub fn rgb(&self) -> Vec3 {
+    Vec3::new(self.r, self.g, self.b)
+  }
+
+  // Luminancia relativa (Rec. 709)
+  pub fn luminance(&self) -> f32 {
+    0.2126 * self.r + 0.7152 * self.g + 0.0722 * self.b
+  }
+
+  // Opacidad en [0, 1]
+  pub fn alpha(&self) -> f32 {
+    self.a
+  }
+
+  // Mismo color multiplicando su opacidad por `opacity`
+  pub fn fade(&self, opacity: f32) -> Self {
+    Color { a: (self.a * opacity).clamp(0.0, 1.0), ..*self }
+  }
+
+  // Combina este color (el fragmento) sobre `destination` (lo que ya está dibujado)
+  pub fn blend_onto(&self, destination: Color, mode: BlendMode) -> Color {
+    let alpha = self.alpha();
+    let channel = |source: f32, dest: f32| match mode {
+      BlendMode::Alpha => source * alpha + dest * (1.0 - alpha),
+      BlendMode::Additive => dest + source * alpha,
+      BlendMode::Multiply => dest * (1.0 - alpha + alpha * source),
+    };
     Color {
-      r: (r.clamp(0.0, 1.0) * 255.0) as u8,
-      g: (g.clamp(0.0, 1.0) * 255.0) as u8,
-      b: (b.clamp(0.0, 1.0) * 255.0) as u8,
+      r: channel(self.r, destination.r),
+      g: channel(self.g, destination.g),
+      b: channel(self.b, destination.b),
+      a: destination.a,
     }
   }
 
-  // Function to create a color from a hex value
-  pub fn from_hex(hex: u32) -> Self {
-    let r = ((hex >> 16) & 0xFF) as u8;
-    let g = ((hex >> 8) & 0xFF) as u8;
-    let b = (hex & 0xFF) as u8;
-    Color { r, g, b }
+  // Linear interpolation between two colors (t in 0.0..=1.0)
+  pub fn lerp(&self, other: &Color, t: f32) -> Self {
+    let t = t.clamp(0.0, 1.0);
+    Color {
+      r: self.r + (other.r - self.r) * t,
+      g: self.g + (other.g - self.g) * t,
+      b: self.b + (other.b - self.b) * t,
+      a: self.a + (other.a - self.a) * t,
+    }
+  }
+
+  // Multiplica cada canal por su propio factor (p. ej. la luz de color que recibe)
+  pub fn scale_channels(&self, r: f32, g: f32, b: f32) -> Self {
+    Color {
+      r: (self.r * r).max(0.0),
+      g: (self.g * g).max(0.0),
+      b: (self.b * b).max(0.0),
+      a: self.a,
+    }
   }
 
-  // Function to return the color as a hex value
+  // Function to return the color as a hex value (sRGB, recortado a [0, 1])
   pub fn to_hex(&self) -> u32 {
-    ((self.r as u32) << 16) | ((self.g as u32) << 8) | (self.b as u32)
+    ((encode(self.r) as u32) << 16) | ((encode(self.g) as u32) << 8) | (encode(self.b) as u32)
   }
 }
 
@@ -49,9 +156,10 @@ impl Add for Color {
 
   fn add(self, other: Color) -> Color {
     Color {
-      r: self.r.saturating_add(other.r),
-      g: self.g.saturating_add(other.g),
-      b: self.b.saturating_add(other.b),
+      r: self.r + other.r,
+      g: self.g + other.g,
+      b: self.b + other.b,
+      a: self.a.max(other.a),
     }
   }
 }
@@ -64,9 +172,24 @@ impl Mul<f32> for Color {
 
   fn mul(self, scalar: f32) -> Color {
     Color {
-      r: (self.r as f32 * scalar).clamp(0.0, 255.0) as u8,
-      g: (self.g as f32 * scalar).clamp(0.0, 255.0) as u8,
-      b: (self.b as f32 * scalar).clamp(0.0, 255.0) as u8,
+      r: (self.r * scalar).max(0.0),
+      g: (self.g * scalar).max(0.0),
+      b: (self.b * scalar).max(0.0),
+      a: self.a,
+    }
+  }
+}
+
+// Component-wise multiplication (modulate one color by another)
+impl Mul<Color> for Color {
+  type Output = Color;
+
+  fn mul(self, other: Color) -> Color {
+    Color {
+      r: self.r * other.r,
+      g: self.g * other.g,
+      b: self.b * other.b,
+      a: self.a * other.a,
     }
   }
 }
@@ -74,6 +197,50 @@ impl Mul<f32> for Color {
 // Implement display formatting for Color
 impl fmt::Display for Color {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-    write!(f, "Color(r: {}, g: {}, b: {})", self.r, self.g, self.b)
+    write!(f, "Color(r: {:.3}, g: {:.3}, b: {:.3}, a: {:.3})", self.r, self.g, self.b, self.a)
   }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn assert_rgb(color: Color, expected: [f32; 3]) {
+    let rgb = color.rgb();
+    assert!((0..3).all(|i| (rgb[i] - expected[i]).abs() < 1e-6), "{:?} != {:?}", rgb, expected);
+  }
+
+  #[test]
+  fn alpha_blend_mixes_by_opacity() {
+    let source = Color::from_float(1.0, 0.0, 0.5).fade(0.25);
+    let destination = Color::from_float(0.0, 1.0, 0.5);
+    assert_rgb(source.blend_onto(destination, BlendMode::Alpha), [0.25, 0.75, 0.5]);
+    // Opaco reemplaza y transparente no cambia nada
+    assert_rgb(source.fade(4.0).blend_onto(destination, BlendMode::Alpha), [1.0, 0.0, 0.5]);
+    assert_rgb(source.fade(0.0).blend_onto(destination, BlendMode::Alpha), [0.0, 1.0, 0.5]);
+  }
+
+  #[test]
+  fn additive_blend_adds_light_without_saturating() {
+    let source = Color::from_float(2.0, 1.0, 0.0).fade(0.5);
+    let destination = Color::from_float(0.5, 0.5, 0.5);
+    assert_rgb(source.blend_onto(destination, BlendMode::Additive), [1.5, 1.0, 0.5]);
+  }
+
+  #[test]
+  fn multiply_blend_tints_destination() {
+    let source = Color::from_float(0.5, 1.0, 0.0);
+    let destination = Color::from_float(0.8, 0.8, 0.8);
+    assert_rgb(source.blend_onto(destination, BlendMode::Multiply), [0.4, 0.8, 0.0]);
+    assert_rgb(source.fade(0.5).blend_onto(destination, BlendMode::Multiply), [0.6, 0.8, 0.4]);
+  }
+
+  #[test]
+  fn blend_keeps_destination_opacity() {
+    let destination = Color::from_float(0.2, 0.2, 0.2);
+    for mode in [BlendMode::Alpha, BlendMode::Additive, BlendMode::Multiply] {
+      let blended = Color::from_float(1.0, 1.0, 1.0).fade(0.3).blend_onto(destination, mode);
+      assert_eq!(blended.alpha(), 1.0);
+    }
+  }
+}
@@ -1,18 +1,16 @@
-use nalgebra_glm::Vec2;
+use nalgebra_glm::{Vec2, Vec3};
 use crate::color::Color;
 
 pub struct Fragment {
-    pub position: Vec2,
-    pub color: Color,
-    pub depth: f32,
+    // Varyings interpolados con corrección de perspectiva
+    pub color: Color,          // Color de vértice (tiñe el albedo; blanco = sin tinte)
+    pub normal: Vec3,          // Normal en coordenadas de mundo
+    pub object_position: Vec3, // Posición en espacio del modelo
+    pub object_normal: Vec3,   // Normal y tangente en espacio del modelo (sin normalizar)
+    pub tangent: Vec3,
+    pub world_position: Vec3,
+    pub tex_coords: Vec2,
+    // Cambio de las UV al avanzar un píxel en x / en y (para el mipmapping)
+    pub tex_coords_dx: Vec2,
+    pub tex_coords_dy: Vec2,
 }
-
-impl Fragment {
-    pub fn new(x: f32, y: f32, color: Color, depth: f32) -> Self {
-        Fragment {
-            position: Vec2::new(x, y),
-            color,
-            depth,
-        }
-    }
-}
\ No newline at end of file
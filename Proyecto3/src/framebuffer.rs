@@ -1,10 +1,68 @@
 // framebuffer.rs
+//
+// La escena se acumula en `hdr` (RGB lineal sin límite superior) y `resolve` la lleva al
+// buffer de pantalla 0xRRGGBB con un operador de tone mapping. La UI se dibuja después,
+// directamente sobre el buffer de pantalla.
+// Con multisampling la escena se rasteriza en `color_samples`/`depth_samples` (varias
+// muestras por píxel) y `resolve_samples` las promedia en `hdr` y `zbuffer`.
+
+use nalgebra_glm::Vec3;
+use crate::antialiasing::AntiAliasing;
+use crate::color::{BlendMode, Color};
+use crate::hdr::ToneMapping;
+
+// Tamaño (en píxeles) de los tiles cuadrados usados por el rasterizador
+pub const TILE_SIZE: usize = 32;
+
+// Franja horizontal de tiles con acceso exclusivo a sus filas del framebuffer,
+// para que cada hilo pueda escribir sin sincronización
+pub struct TileRow<'a> {
+    pub y0: usize,
+    pub width: usize,
+    pub height: usize,
+    // Posición de cada muestra del píxel respecto a su centro
+    pub sample_offsets: &'static [(f32, f32)],
+    buffer: &'a mut [Color],
+    zbuffer: &'a mut [f32],
+}
+
+impl TileRow<'_> {
+    // Muestra `sample` del píxel (x, y); `y` está en coordenadas del framebuffer completo
+    pub fn point(&mut self, x: usize, y: usize, sample: usize, color: Color, depth: f32) {
+        if let Some(index) = self.index(x, y, sample)
+            && self.zbuffer[index] > depth
+        {
+            self.buffer[index] = color;
+            self.zbuffer[index] = depth;
+        }
+    }
+
+    // Fragmento transparente: respeta la profundidad de lo opaco pero no la escribe
+    pub fn blend(&mut self, x: usize, y: usize, sample: usize, color: Color, depth: f32, mode: BlendMode) {
+        if let Some(index) = self.index(x, y, sample)
+            && self.zbuffer[index] > depth
+        {
+            self.buffer[index] = color.blend_onto(self.buffer[index], mode);
+        }
+    }
+
+    fn index(&self, x: usize, y: usize, sample: usize) -> Option<usize> {
+        (x < self.width && y >= self.y0 && y < self.y0 + self.height)
+            .then(|| ((y - self.y0) * self.width + x) * self.sample_offsets.len() + sample)
+    }
+}
 
 pub struct Framebuffer {
     pub width: usize,
     pub height: usize,
+    // Imagen final en pantalla (después de `resolve`)
     pub buffer: Vec<u32>,
+    pub hdr: Vec<Color>,
     pub zbuffer: Vec<f32>,
+    anti_aliasing: AntiAliasing,
+    // Vacíos con una sola muestra por píxel: entonces se rasteriza directo en `hdr`
+    color_samples: Vec<Color>,
+    depth_samples: Vec<f32>,
     background_color: u32,
     current_color: u32,
 }
@@ -15,31 +73,148 @@ impl Framebuffer {
             width,
             height,
             buffer: vec![0; width * height],
+            hdr: vec![Color::black(); width * height],
             zbuffer: vec![f32::INFINITY; width * height],
+            anti_aliasing: AntiAliasing::Off,
+            color_samples: Vec::new(),
+            depth_samples: Vec::new(),
             background_color: 0x000000,
             current_color: 0xFFFFFF,
         }
     }
 
-    pub fn clear(&mut self) {
-        for pixel in self.buffer.iter_mut() {
-            *pixel = self.background_color;
+    pub fn anti_aliasing(&self) -> AntiAliasing {
+        self.anti_aliasing
+    }
+
+    // Cambia el modo de antialiasing; reserva las muestras si el modo tiene más de una
+    pub fn set_anti_aliasing(&mut self, mode: AntiAliasing) {
+        self.anti_aliasing = mode;
+        let samples = self.samples_per_pixel();
+        if samples > 1 {
+            let count = self.width * self.height * samples;
+            self.color_samples = vec![Color::black(); count];
+            self.depth_samples = vec![f32::INFINITY; count];
+        } else {
+            self.color_samples = Vec::new();
+            self.depth_samples = Vec::new();
         }
-        for depth in self.zbuffer.iter_mut() {
-            *depth = f32::INFINITY;
+    }
+
+    fn samples_per_pixel(&self) -> usize {
+        self.anti_aliasing.sample_offsets().len()
+    }
+
+    // Color y profundidad en los que se rasteriza
+    fn targets(&mut self) -> (&mut [Color], &mut [f32]) {
+        if self.samples_per_pixel() > 1 {
+            (&mut self.color_samples, &mut self.depth_samples)
+        } else {
+            (&mut self.hdr, &mut self.zbuffer)
         }
     }
 
+    pub fn clear(&mut self) {
+        let background = Color::from_hex(self.background_color);
+        let (colors, depths) = self.targets();
+        colors.fill(background);
+        depths.fill(f32::INFINITY);
+    }
+
+    // Puntos de la escena (estrellas, órbitas): cubren todas las muestras del píxel
     pub fn point(&mut self, x: usize, y: usize, depth: f32) {
         if x < self.width && y < self.height {
-            let index = y * self.width + x;
-            if self.zbuffer[index] > depth {
-                self.buffer[index] = self.current_color;
-                self.zbuffer[index] = depth;
+            let color = Color::from_hex(self.current_color);
+            let samples = self.samples_per_pixel();
+            let first = (y * self.width + x) * samples;
+            let (colors, depths) = self.targets();
+            for index in first..first + samples {
+                if depths[index] > depth {
+                    colors[index] = color;
+                    depths[index] = depth;
+                }
             }
         }
     }
 
+    // Fragmento transparente (líneas) sobre todas las muestras del píxel: se mezcla solo en
+    // las que lo opaco no tapa y no escribe profundidad
+    pub fn blend(&mut self, x: usize, y: usize, color: Color, depth: f32, mode: BlendMode) {
+        if x < self.width && y < self.height {
+            let samples = self.samples_per_pixel();
+            let first = (y * self.width + x) * samples;
+            let (colors, depths) = self.targets();
+            for index in first..first + samples {
+                if depths[index] > depth {
+                    colors[index] = color.blend_onto(colors[index], mode);
+                }
+            }
+        }
+    }
+
+    // Promedia las muestras de cada píxel en `hdr`; la profundidad es la más cercana.
+    // Sin multisampling no hace nada
+    pub fn resolve_samples(&mut self) {
+        let samples = self.samples_per_pixel();
+        if samples == 1 {
+            return;
+        }
+        let weight = 1.0 / samples as f32;
+        let pixels = self.color_samples.chunks(samples).zip(self.depth_samples.chunks(samples));
+        for ((pixel, depth), (colors, depths)) in self.hdr.iter_mut().zip(self.zbuffer.iter_mut()).zip(pixels) {
+            *pixel = colors.iter().fold(Color::from_float(0.0, 0.0, 0.0), |sum, &color| sum + color * weight);
+            *depth = depths.iter().copied().fold(f32::INFINITY, f32::min);
+        }
+    }
+
+    // Escribe en el buffer de pantalla sin pasar por el HDR ni la profundidad (UI)
+    pub fn overlay(&mut self, x: usize, y: usize, color: u32) {
+        if x < self.width && y < self.height {
+            self.buffer[y * self.width + x] = color;
+        }
+    }
+
+    // Pasa la imagen HDR al buffer de pantalla: escala por `exposure` y aplica la curva
+    pub fn resolve(&mut self, tone_mapping: ToneMapping, exposure: f32) {
+        for (pixel, color) in self.buffer.iter_mut().zip(&self.hdr) {
+            *pixel = tone_mapping.apply(color.rgb() * exposure).to_hex();
+        }
+    }
+
+    pub fn tiles_x(&self) -> usize {
+        self.width.div_ceil(TILE_SIZE)
+    }
+
+    pub fn tiles_y(&self) -> usize {
+        self.height.div_ceil(TILE_SIZE)
+    }
+
+    // Divide el framebuffer en franjas de TILE_SIZE filas
+    pub fn tile_rows(&mut self) -> Vec<TileRow<'_>> {
+        let width = self.width;
+        let height = self.height;
+        let sample_offsets = self.anti_aliasing.sample_offsets();
+        let chunk = width * TILE_SIZE * sample_offsets.len();
+        let (colors, depths) = self.targets();
+
+        colors
+            .chunks_mut(chunk)
+            .zip(depths.chunks_mut(chunk))
+            .enumerate()
+            .map(|(i, (buffer, zbuffer))| {
+                let y0 = i * TILE_SIZE;
+                TileRow {
+                    y0,
+                    width,
+                    height: TILE_SIZE.min(height - y0),
+                    sample_offsets,
+                    buffer,
+                    zbuffer,
+                }
+            })
+            .collect()
+    }
+
     pub fn set_background_color(&mut self, color: u32) {
         self.background_color = color;
     }
@@ -47,4 +222,15 @@ impl Framebuffer {
     pub fn set_current_color(&mut self, color: u32) {
         self.current_color = color;
     }
-}
\ No newline at end of file
+}
+
+// Píxel 0xRRGGBB del buffer de pantalla a RGB en [0, 1] y de vuelta, para los filtros que
+// trabajan sobre la imagen ya resuelta (FXAA, post-proceso)
+pub(crate) fn unpack_pixel(pixel: u32) -> Vec3 {
+    Vec3::new(((pixel >> 16) & 0xFF) as f32, ((pixel >> 8) & 0xFF) as f32, (pixel & 0xFF) as f32) / 255.0
+}
+
+pub(crate) fn pack_pixel(color: Vec3) -> u32 {
+    let channel = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u32;
+    (channel(color.x) << 16) | (channel(color.y) << 8) | channel(color.z)
+}
@@ -0,0 +1,483 @@
+// golden_tests.rs - Regresión visual de los shaders procedurales contra imágenes de referencia
+//
+// Cada ShaderType (y una textura de prueba) se renderiza sobre la esfera en tiempos fijos
+// y se compara con `tests/golden/<nombre>.png`. Para regenerar las referencias después de
+// un cambio intencional: `UPDATE_GOLDEN=1 cargo test golden`.
+// Si algo no coincide se escribe `target/golden-diff/<nombre>_{actual,diff}.png`.
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+use nalgebra_glm::{Mat4, Vec2, Vec3};
+use crate::bloom::{Bloom, BloomSettings};
+use crate::line::{draw_line, LineStyle};
+use crate::lut::Lut;
+use crate::postprocess::{ChromaticAberration, Dithering, FilmGrain, PostEffect, PostProcess, Vignette};
+use crate::antialiasing::{AntiAliasing, Fxaa};
+use crate::atmosphere::{Atmosphere, AtmosphereDescription};
+use crate::camera::Camera;
+use crate::clouds::{CloudLayer, CloudsDescription};
+use crate::framebuffer::Framebuffer;
+use crate::hdr::{HdrSettings, ToneMapping};
+use crate::image_io::{load_png, save_png};
+use crate::color::{BlendMode, Color};
+use crate::triangle::{CullMode, RenderState, ShaderParams, ShaderType};
+use crate::obj::Obj;
+use crate::vertex::Vertex;
+use crate::light::{Light, LightKind};
+use crate::ring::{Ring, RingDescription};
+use crate::shadow::Occluder;
+use crate::texture::{FilterMode, NormalMap, SurfaceTexture, Texture, TextureMode, WrapMode};
+use crate::{create_model_matrix, create_normal_matrix, create_viewport_matrix, render, Uniforms};
+
+const SIZE: usize = 96;
+const TIMES: [f32; 2] = [0.0, 2.5];
+
+// Diferencia máxima por canal (0-255) para considerar un píxel igual
+const CHANNEL_TOLERANCE: u8 = 8;
+// Píxeles distintos permitidos por imagen (bordes y umbrales de ruido con floats)
+const MAX_MISMATCHED_PIXELS: usize = SIZE * SIZE / 500;
+
+// Luz arriba a la izquierda del observador, para que se vea el terminador
+const GOLDEN_LIGHT: Light = Light {
+    kind: LightKind::Point { position: Vec3::new(-6.0, 4.0, 4.0), falloff_distance: 1000.0, radius: 0.6 },
+    color: Vec3::new(1.0, 1.0, 1.0),
+    intensity: 1.0,
+};
+
+fn manifest_path(relative: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(relative)
+}
+
+fn sphere_vertices() -> Vec<Vertex> {
+    Obj::load(manifest_path("assets/models/sphere.obj").to_str().unwrap())
+        .expect("Failed to load sphere.obj")
+        .get_vertex_array()
+}
+
+fn new_framebuffer() -> Framebuffer {
+    let mut framebuffer = Framebuffer::new(SIZE, SIZE);
+    framebuffer.clear();
+    framebuffer
+}
+
+// Cámara en (0, 0, 3) mirando al origen, iluminada por GOLDEN_LIGHT
+fn golden_uniforms(model_matrix: Mat4, time: f32) -> Uniforms {
+    let mut camera = Camera::new(Vec3::new(0.0, 0.0, 3.0));
+    camera.look_at(Vec3::new(0.0, 0.0, 0.0));
+
+    Uniforms {
+        model_matrix,
+        view_matrix: camera.get_view_matrix(),
+        projection_matrix: camera.get_projection_matrix(1.0),
+        viewport_matrix: create_viewport_matrix(SIZE as f32, SIZE as f32),
+        normal_matrix: create_normal_matrix(&model_matrix),
+        time,
+        shader_params: ShaderParams::default(),
+        texture: None,
+        normal_map: None,
+        atmosphere: None,
+        clouds: None,
+        camera_position: camera.position,
+        lights: Arc::new([GOLDEN_LIGHT]),
+        occluders: Arc::new([]),
+        ambient: 0.05,
+        emissive: false,
+    }
+}
+
+// `configure` ajusta los uniforms por defecto (textura, sombras...) antes de dibujar
+fn render_surface(shader_type: Option<ShaderType>, time: f32, configure: impl FnOnce(&mut Uniforms)) -> Framebuffer {
+    render_surface_with(AntiAliasing::Off, shader_type, time, configure)
+}
+
+// Igual que render_surface, con las muestras de `anti_aliasing` ya promediadas en `hdr`
+fn render_surface_with(anti_aliasing: AntiAliasing, shader_type: Option<ShaderType>, time: f32, configure: impl FnOnce(&mut Uniforms)) -> Framebuffer {
+    let mut framebuffer = Framebuffer::new(SIZE, SIZE);
+    framebuffer.set_anti_aliasing(anti_aliasing);
+    framebuffer.clear();
+
+    let model_matrix = create_model_matrix(Vec3::new(0.0, 0.0, 0.0), 1.0, Vec3::new(0.3, time * 0.2, 0.0));
+    let mut uniforms = golden_uniforms(model_matrix, time);
+    uniforms.emissive = shader_type == Some(ShaderType::Sun);
+    configure(&mut uniforms);
+    render(&mut framebuffer, &uniforms, &sphere_vertices(), shader_type, RenderState::opaque(CullMode::Back));
+    framebuffer.resolve_samples();
+
+    framebuffer
+}
+
+fn channel_diff(a: u32, b: u32) -> u8 {
+    (0..3)
+        .map(|i| {
+            let shift = i * 8;
+            (((a >> shift) & 0xFF) as i32 - ((b >> shift) & 0xFF) as i32).unsigned_abs() as u8
+        })
+        .max()
+        .unwrap()
+}
+
+// Imagen de diferencias: la referencia atenuada en gris y en rojo los píxeles que fallan
+fn diff_image(expected: &[u32], actual: &[u32]) -> Framebuffer {
+    let mut diff = Framebuffer::new(SIZE, SIZE);
+    for (i, (&e, &a)) in expected.iter().zip(actual).enumerate() {
+        let d = channel_diff(e, a);
+        diff.buffer[i] = if d > CHANNEL_TOLERANCE {
+            0xFF0000 | (((d as u32) / 2) << 8)
+        } else {
+            let gray = (((e >> 16) & 0xFF) + ((e >> 8) & 0xFF) + (e & 0xFF)) / 12;
+            (gray << 16) | (gray << 8) | gray
+        };
+    }
+    diff
+}
+
+// Tablero de 8x4 casillas sobre la esfera (UV esféricas), para probar el muestreo de texturas
+fn checker_texture() -> SurfaceTexture {
+    let (width, height) = (64, 32);
+    let texels = (0..width * height)
+        .map(|i| {
+            let (x, y) = (i % width, i / width);
+            if (x / 8 + y / 8) % 2 == 0 { Vec3::new(0.9, 0.3, 0.1) } else { Vec3::new(0.1, 0.4, 0.9) }
+        })
+        .collect();
+    SurfaceTexture {
+        texture: Arc::new(Texture::from_texels(width, height, texels, FilterMode::Bilinear, WrapMode::Repeat)),
+        mode: TextureMode::Replace,
+    }
+}
+
+// Normal map de cúpulas: cada celda de 8x8 texels se inclina hacia fuera de su centro
+fn dome_normal_map() -> NormalMap {
+    let (width, height) = (64, 32);
+    let texels = (0..width * height)
+        .map(|i| {
+            let (x, y) = (i % width, i / width);
+            let dx = ((x % 8) as f32 + 0.5 - 4.0) / 4.0;
+            let dy = ((y % 8) as f32 + 0.5 - 4.0) / 4.0;
+            // El verde apunta hacia arriba en la imagen (-v)
+            let normal = Vec3::new(dx, -dy, 1.0).normalize();
+            (normal + Vec3::repeat(1.0)) * 0.5
+        })
+        .collect();
+    NormalMap {
+        texture: Arc::new(Texture::from_texels(width, height, texels, FilterMode::Bilinear, WrapMode::Repeat)),
+        strength: 1.0,
+    }
+}
+
+fn check_golden(shader_type: ShaderType, name: &str) {
+    check_golden_with(name, |time| render_surface(Some(shader_type), time, |_| {}));
+}
+
+// Las imágenes se comparan después del tone mapping por defecto de las escenas
+fn check_golden_with(name: &str, render_frame: impl Fn(f32) -> Framebuffer) {
+    check_golden_tone_mapped(name, HdrSettings::default().tone_mapping, render_frame);
+}
+
+fn check_golden_tone_mapped(name: &str, tone_mapping: ToneMapping, render_frame: impl Fn(f32) -> Framebuffer) {
+    check_golden_resolved(name, |time| {
+        let mut framebuffer = render_frame(time);
+        framebuffer.resolve(tone_mapping, 1.0);
+        framebuffer
+    });
+}
+
+// `render_frame` entrega la imagen ya en pantalla (`buffer`), p. ej. con post-proceso
+fn check_golden_resolved(name: &str, render_frame: impl Fn(f32) -> Framebuffer) {
+    for time in TIMES {
+        let image_name = format!("{}_t{:.1}", name, time).replace('.', "_");
+        let golden_path = manifest_path(&format!("tests/golden/{}.png", image_name));
+        let actual = render_frame(time);
+
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            fs::create_dir_all(golden_path.parent().unwrap()).unwrap();
+            save_png(&actual, &golden_path).unwrap();
+            continue;
+        }
+
+        let (width, height, expected) = load_png(&golden_path)
+            .unwrap_or_else(|e| panic!("no se pudo leer {}: {} (UPDATE_GOLDEN=1 para generarla)", golden_path.display(), e));
+        assert_eq!((width, height), (SIZE, SIZE), "tamaño distinto en {}", golden_path.display());
+
+        let mismatched = expected
+            .iter()
+            .zip(&actual.buffer)
+            .filter(|&(&e, &a)| channel_diff(e, a) > CHANNEL_TOLERANCE)
+            .count();
+
+        if mismatched > MAX_MISMATCHED_PIXELS {
+            let diff_dir = manifest_path("target/golden-diff");
+            fs::create_dir_all(&diff_dir).unwrap();
+            save_png(&actual, &diff_dir.join(format!("{}_actual.png", image_name))).unwrap();
+            save_png(&diff_image(&expected, &actual.buffer), &diff_dir.join(format!("{}_diff.png", image_name))).unwrap();
+            panic!(
+                "{}: {} píxeles fuera de tolerancia (máximo {}); ver {}",
+                image_name,
+                mismatched,
+                MAX_MISMATCHED_PIXELS,
+                diff_dir.display()
+            );
+        }
+    }
+}
+
+#[test]
+fn golden_sun() {
+    check_golden(ShaderType::Sun, "sun");
+}
+
+#[test]
+fn golden_rocky_planet() {
+    check_golden(ShaderType::RockyPlanet, "rocky_planet");
+}
+
+#[test]
+fn golden_gas_giant() {
+    check_golden(ShaderType::GasGiant, "gas_giant");
+}
+
+#[test]
+fn golden_ice_planet() {
+    check_golden(ShaderType::IcePlanet, "ice_planet");
+}
+
+#[test]
+fn golden_volcanic_planet() {
+    check_golden(ShaderType::VolcanicPlanet, "volcanic_planet");
+}
+
+#[test]
+fn golden_moon() {
+    check_golden(ShaderType::Moon, "moon");
+}
+
+#[test]
+fn golden_spaceship() {
+    check_golden(ShaderType::Spaceship, "spaceship");
+}
+
+#[test]
+fn golden_checker_texture() {
+    check_golden_with("checker_texture", |time| {
+        render_surface(None, time, |uniforms| uniforms.texture = Some(checker_texture()))
+    });
+}
+
+#[test]
+fn golden_normal_map() {
+    check_golden_with("normal_map", |time| {
+        render_surface(None, time, |uniforms| uniforms.normal_map = Some(dome_normal_map()))
+    });
+}
+
+// Gigante gaseoso con anillo inclinado: los gaps dejan ver el planeta y el fondo, el
+// anillo pasa por delante y por detrás del planeta y queda a la sombra de este
+#[test]
+fn golden_ring() {
+    let ring = Ring::new(&RingDescription { inner_radius: 1.4, outer_radius: 2.3, tilt: 0.45 }).unwrap();
+    check_golden_with("ring", |time| {
+        let mut framebuffer = new_framebuffer();
+
+        let planet_model = create_model_matrix(Vec3::new(0.0, 0.0, 0.0), 0.55, Vec3::new(0.3, time * 0.2, 0.0));
+        let planet = golden_uniforms(planet_model, time);
+        render(&mut framebuffer, &planet, &sphere_vertices(), Some(ShaderType::GasGiant), RenderState::opaque(CullMode::Back));
+
+        let ring_model = create_model_matrix(Vec3::new(0.0, 0.0, 0.0), 0.55, Vec3::new(ring.tilt, 0.0, 0.0));
+        let mut ring_uniforms = golden_uniforms(ring_model, time);
+        // El planeta hace sombra sobre el anillo
+        ring_uniforms.occluders = Arc::new([Occluder { center: Vec3::new(0.0, 0.0, 0.0), radius: 0.55 }]);
+        render(&mut framebuffer, &ring_uniforms, &ring.vertices, Some(ShaderType::Ring), RenderState::transparent(BlendMode::Alpha));
+
+        framebuffer
+    });
+}
+
+// Capa de hielo translúcida sobre un planeta rocoso: la pasada transparente ve la
+// profundidad del planeta pero no la escribe, así que se ven las dos caras de la capa
+#[test]
+fn golden_transparent_shell() {
+    check_golden_with("transparent_shell", |time| {
+        let mut framebuffer = new_framebuffer();
+
+        let planet_model = create_model_matrix(Vec3::new(0.0, 0.0, 0.0), 0.6, Vec3::new(0.3, time * 0.2, 0.0));
+        let planet = golden_uniforms(planet_model, time);
+        render(&mut framebuffer, &planet, &sphere_vertices(), Some(ShaderType::RockyPlanet), RenderState::opaque(CullMode::Back));
+
+        let shell_model = create_model_matrix(Vec3::new(0.0, 0.0, 0.0), 0.8, Vec3::new(0.3, time * 0.2, 0.0));
+        let mut shell = golden_uniforms(shell_model, time);
+        shell.shader_params.opacity = 0.35;
+        render(&mut framebuffer, &shell, &sphere_vertices(), Some(ShaderType::IcePlanet), RenderState::transparent(BlendMode::Alpha));
+
+        framebuffer
+    });
+}
+
+// Un cuadrado translúcido partido en dos triángulos por una diagonal que pasa justo por los
+// centros (y las muestras) de los píxeles: cada muestra de la diagonal se mezcla una sola vez
+#[test]
+fn shared_edge_is_blended_once() {
+    let corner = |x: f32, y: f32| Vertex::new(Vec3::new(x, y, 0.0), Vec3::new(0.0, 0.0, 1.0), Vec2::zeros());
+    let quad = [
+        corner(-0.5, -0.5), corner(0.5, -0.5), corner(0.5, 0.5),
+        corner(-0.5, -0.5), corner(0.5, 0.5), corner(-0.5, 0.5),
+    ];
+    for anti_aliasing in [AntiAliasing::Off, AntiAliasing::OrderedGrid, AntiAliasing::RotatedGrid] {
+        let mut framebuffer = Framebuffer::new(SIZE, SIZE);
+        framebuffer.set_anti_aliasing(anti_aliasing);
+        framebuffer.clear();
+        let background = framebuffer.hdr[0];
+
+        // Sin vista ni proyección: el cuadrado cubre los píxeles [24, 72) en los dos ejes
+        let mut uniforms = golden_uniforms(Mat4::identity(), 0.0);
+        uniforms.view_matrix = Mat4::identity();
+        uniforms.projection_matrix = Mat4::identity();
+        uniforms.emissive = true;
+        uniforms.shader_params.opacity = 0.5;
+        render(&mut framebuffer, &uniforms, &quad, None, RenderState::transparent(BlendMode::Alpha));
+        framebuffer.resolve_samples();
+
+        let once = Color::new(255, 255, 255).fade(0.5).blend_onto(background, BlendMode::Alpha);
+        let matches = |a: Color, b: Color| (0..3).all(|i| (a.rgb()[i] - b.rgb()[i]).abs() < 1e-4);
+        for y in 0..SIZE {
+            for x in 0..SIZE {
+                let expected = if (24..72).contains(&x) && (24..72).contains(&y) { once } else { background };
+                let actual = framebuffer.hdr[y * SIZE + x];
+                assert!(matches(actual, expected), "{:?}: ({}, {}) = {:?}, se esperaba {:?}", anti_aliasing, x, y, actual.rgb(), expected.rgb());
+            }
+        }
+    }
+}
+
+// Planeta rocoso con atmósfera: borde azul del lado de día y terminador suavizado
+#[test]
+fn golden_atmosphere() {
+    let atmosphere = Atmosphere::new(&AtmosphereDescription { color: [0.25, 0.5, 1.0], density: 1.0, height: 0.15 }).unwrap();
+    check_golden_with("atmosphere", |time| {
+        let mut framebuffer = new_framebuffer();
+
+        let planet_model = create_model_matrix(Vec3::new(0.0, 0.0, 0.0), 0.75, Vec3::new(0.3, time * 0.2, 0.0));
+        let planet = golden_uniforms(planet_model, time);
+        render(&mut framebuffer, &planet, &sphere_vertices(), Some(ShaderType::RockyPlanet), RenderState::opaque(CullMode::Back));
+
+        let shell_model = create_model_matrix(Vec3::new(0.0, 0.0, 0.0), atmosphere.shell_scale(0.75), Vec3::new(0.0, 0.0, 0.0));
+        let mut shell = golden_uniforms(shell_model, time);
+        shell.atmosphere = Some(atmosphere);
+        let state = RenderState { cull_mode: CullMode::Back, blend_mode: Some(BlendMode::Additive) };
+        render(&mut framebuffer, &shell, &sphere_vertices(), Some(ShaderType::Atmosphere), state);
+
+        framebuffer
+    });
+}
+
+// Capa de nubes girando sobre un planeta rocoso, con su sombra sobre el suelo
+#[test]
+fn golden_clouds() {
+    let description = CloudsDescription { coverage: 0.5, height: 0.04, ..CloudsDescription::default() };
+    check_golden_with("clouds", |time| {
+        let mut framebuffer = new_framebuffer();
+        let mut clouds = CloudLayer::new(&description).unwrap();
+        clouds.rotation = time * 0.4;
+
+        let planet_model = create_model_matrix(Vec3::new(0.0, 0.0, 0.0), 0.75, Vec3::new(0.3, time * 0.2, 0.0));
+        let mut planet = golden_uniforms(planet_model, time);
+        planet.clouds = Some(clouds);
+        render(&mut framebuffer, &planet, &sphere_vertices(), Some(ShaderType::RockyPlanet), RenderState::opaque(CullMode::Back));
+
+        let shell_model = create_model_matrix(Vec3::new(0.0, 0.0, 0.0), clouds.shell_scale(0.75), Vec3::new(0.0, 0.0, 0.0));
+        let mut shell = golden_uniforms(shell_model, time);
+        shell.clouds = Some(clouds);
+        let state = RenderState { cull_mode: CullMode::Back, blend_mode: Some(BlendMode::Alpha) };
+        render(&mut framebuffer, &shell, &sphere_vertices(), Some(ShaderType::Clouds), state);
+
+        framebuffer
+    });
+}
+
+// Sol sobreexpuesto con cada operador: Linear recorta el disco a blanco plano, Reinhard y
+// Aces conservan el gradiente y las manchas
+#[test]
+fn golden_tone_mapping() {
+    for tone_mapping in [ToneMapping::Linear, ToneMapping::Reinhard, ToneMapping::Aces] {
+        let name = format!("tone_mapping_{:?}", tone_mapping).to_lowercase();
+        check_golden_tone_mapped(&name, tone_mapping, |time| {
+            render_surface(Some(ShaderType::Sun), time, |uniforms| uniforms.shader_params.brightness = 3.0)
+        });
+    }
+}
+
+// El Sol por encima del umbral resplandece más allá de su silueta
+#[test]
+fn golden_bloom() {
+    check_golden_with("bloom", |time| {
+        let mut framebuffer = render_surface(Some(ShaderType::Sun), time, |uniforms| uniforms.shader_params.brightness = 4.0);
+        Bloom::default().apply(&BloomSettings::default(), &mut framebuffer);
+        framebuffer
+    });
+}
+
+// Toda la cadena de post-proceso, con la LUT de ejemplo, sobre el planeta rocoso
+#[test]
+fn golden_post_process() {
+    let lut = Arc::new(Lut::load(&manifest_path("assets/luts/cinematic.cube")).unwrap());
+    check_golden_resolved("post_process", |time| {
+        let mut framebuffer = render_surface(Some(ShaderType::RockyPlanet), time, |_| {});
+        framebuffer.resolve(HdrSettings::default().tone_mapping, 1.0);
+        let mut post_process = PostProcess::new(vec![
+            PostEffect::ColorGrading { enabled: true, lut: lut.clone(), strength: 1.0 },
+            PostEffect::ChromaticAberration(ChromaticAberration { strength: 3.0, ..Default::default() }),
+            PostEffect::Vignette(Vignette::default()),
+            PostEffect::FilmGrain(FilmGrain::default()),
+            PostEffect::Dithering(Dithering::default()),
+        ]);
+        post_process.apply(&mut framebuffer, time);
+        framebuffer
+    });
+}
+
+// La silueta de la esfera con cada modo de antialiasing
+#[test]
+fn golden_anti_aliasing() {
+    for mode in [AntiAliasing::OrderedGrid, AntiAliasing::RotatedGrid, AntiAliasing::Fxaa] {
+        let name = format!("anti_aliasing_{:?}", mode).to_lowercase();
+        check_golden_resolved(&name, |time| {
+            let mut framebuffer = render_surface_with(mode, Some(ShaderType::IcePlanet), time, |_| {});
+            framebuffer.resolve(HdrSettings::default().tone_mapping, 1.0);
+            if mode == AntiAliasing::Fxaa {
+                Fxaa::default().apply(&mut framebuffer);
+            }
+            framebuffer
+        });
+    }
+}
+
+// Una órbita inclinada alrededor de la esfera: la mitad de atrás queda tapada
+#[test]
+fn golden_orbit_line() {
+    check_golden_with("orbit_line", |time| {
+        let mut framebuffer = render_surface(Some(ShaderType::Moon), time, |_| {});
+        let uniforms = golden_uniforms(Mat4::identity(), time);
+        let view_projection = uniforms.projection_matrix * uniforms.view_matrix;
+        let style = LineStyle { color: Color::from_hex(0x66CCFF), width: 1.5 };
+        let point = |angle: f32| {
+            let angle = angle + time * 0.4;
+            Vec3::new(1.3 * angle.cos(), 0.5 * angle.sin(), 1.2 * angle.sin())
+        };
+        let segments = 64;
+        for i in 0..segments {
+            let angle = |i: usize| i as f32 / segments as f32 * std::f32::consts::TAU;
+            draw_line(&mut framebuffer, &view_projection, &uniforms.viewport_matrix, point(angle(i)), point(angle(i + 1)), style);
+        }
+        framebuffer
+    });
+}
+
+// Una luna entre la luz y la esfera: umbra al centro y penumbra alrededor
+#[test]
+fn golden_eclipse() {
+    check_golden_with("eclipse", |time| {
+        render_surface(Some(ShaderType::Moon), time, |uniforms| {
+            uniforms.occluders = Arc::new([Occluder { center: Vec3::new(-2.1, 1.4, 1.4), radius: 0.35 }]);
+        })
+    });
+}
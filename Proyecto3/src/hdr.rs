@@ -0,0 +1,205 @@
+// hdr.rs - Tone mapping y exposición: del framebuffer HDR a colores de pantalla
+//
+// Los shaders escriben luz lineal sin límite (el Sol y su corona pasan de 1.0). Antes de
+// mostrar el frame se multiplica por la exposición y un operador comprime el rango a
+// [0, 1]. Con exposición automática la cámara se adapta poco a poco a la luminancia media
+// de lo que ve, como el ojo: al mirar al Sol los planetas se oscurecen y al volver al
+// lado nocturno se aclaran.
+
+use nalgebra_glm::Vec3;
+use serde::Deserialize;
+use crate::color::Color;
+use crate::framebuffer::Framebuffer;
+
+// Punto blanco de Reinhard: la luminancia que llega justo a 1.0
+const REINHARD_WHITE: f32 = 4.0;
+// Luminancia mínima medida: el lado nocturno cuenta como muy oscuro, no como infinitamente
+const MIN_METERED_LUMINANCE: f32 = 1e-4;
+// Se mide uno de cada METERING_STRIDE píxeles en cada eje
+const METERING_STRIDE: usize = 4;
+
+#[derive(Clone, Copy, PartialEq, Debug, Deserialize)]
+pub enum ToneMapping {
+    // Sin curva: recorta en 1.0, como un framebuffer de 8 bits
+    Linear,
+    // Reinhard extendido sobre la luminancia (conserva el tono de los colores saturados)
+    Reinhard,
+    // Aproximación de la curva fílmica ACES (Narkowicz): más contraste y hombro suave
+    Aces,
+}
+
+impl ToneMapping {
+    pub fn apply(&self, color: Vec3) -> Color {
+        let mapped = match self {
+            ToneMapping::Linear => color,
+            ToneMapping::Reinhard => {
+                let luminance = Color::from_float(color.x, color.y, color.z).luminance();
+                if luminance <= 0.0 {
+                    return Color::black();
+                }
+                let mapped = luminance * (1.0 + luminance / (REINHARD_WHITE * REINHARD_WHITE)) / (1.0 + luminance);
+                color * (mapped / luminance)
+            }
+            ToneMapping::Aces => color.map(|x| {
+                let x = x.max(0.0);
+                (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14)
+            }),
+        };
+        Color::from_float(mapped.x, mapped.y, mapped.z)
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            ToneMapping::Linear => ToneMapping::Reinhard,
+            ToneMapping::Reinhard => ToneMapping::Aces,
+            ToneMapping::Aces => ToneMapping::Linear,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "linear" => Some(ToneMapping::Linear),
+            "reinhard" => Some(ToneMapping::Reinhard),
+            "aces" => Some(ToneMapping::Aces),
+            _ => None,
+        }
+    }
+}
+
+// Sección [hdr] del archivo de escena
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HdrSettings {
+    pub tone_mapping: ToneMapping,
+    // Multiplicador de la luz; con exposición automática es una compensación
+    pub exposure: f32,
+    pub auto_exposure: bool,
+    // Luminancia media a la que se lleva la imagen (gris medio)
+    pub key: f32,
+    // Rapidez con la que se adapta la exposición (1/s); 0 = instantánea
+    pub adaptation_speed: f32,
+    // Límites de la exposición automática
+    pub min_exposure: f32,
+    pub max_exposure: f32,
+}
+
+impl Default for HdrSettings {
+    fn default() -> Self {
+        HdrSettings {
+            tone_mapping: ToneMapping::Aces,
+            exposure: 1.0,
+            auto_exposure: false,
+            key: 0.18,
+            adaptation_speed: 1.5,
+            min_exposure: 0.25,
+            max_exposure: 4.0,
+        }
+    }
+}
+
+impl HdrSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.exposure <= 0.0 || self.key <= 0.0 {
+            return Err("hdr.exposure y hdr.key deben ser positivos".to_string());
+        }
+        if self.adaptation_speed < 0.0 {
+            return Err("hdr.adaptation_speed no puede ser negativa".to_string());
+        }
+        if self.min_exposure <= 0.0 || self.min_exposure > self.max_exposure {
+            return Err("hdr necesita 0 < min_exposure <= max_exposure".to_string());
+        }
+        Ok(())
+    }
+}
+
+// Estado de la exposición automática entre frames
+#[derive(Clone, Copy, Debug, Default)]
+pub struct EyeAdaptation {
+    // Exposición automática actual, sin la compensación; None hasta el primer frame
+    adapted: Option<f32>,
+}
+
+impl EyeAdaptation {
+    // Exposición para el frame que está en `framebuffer.hdr`
+    pub fn update(&mut self, settings: &HdrSettings, framebuffer: &Framebuffer, delta_time: f32) -> f32 {
+        if !settings.auto_exposure {
+            self.adapted = None;
+            return settings.exposure;
+        }
+
+        let target = match average_luminance(framebuffer) {
+            Some(average) => (settings.key / average).clamp(settings.min_exposure, settings.max_exposure),
+            // Nada iluminado a la vista: se mantiene la exposición
+            None => self.adapted.unwrap_or(1.0),
+        };
+
+        // Se acerca al objetivo en escala logarítmica, igual de rápido al aclarar que al oscurecer
+        let adapted = match self.adapted {
+            Some(current) if settings.adaptation_speed > 0.0 => {
+                let blend = 1.0 - (-settings.adaptation_speed * delta_time).exp();
+                (current.ln() + (target.ln() - current.ln()) * blend).exp()
+            }
+            _ => target,
+        };
+        self.adapted = Some(adapted);
+        adapted * settings.exposure
+    }
+}
+
+// Media geométrica de la luminancia de los cuerpos a la vista. Solo cuentan los píxeles
+// con profundidad de NDC: el fondo y las estrellas del skybox quedan fuera, y las órbitas
+// no escriben profundidad, así que no cuentan donde no hay un cuerpo detrás
+fn average_luminance(framebuffer: &Framebuffer) -> Option<f32> {
+    let mut log_sum = 0.0;
+    let mut count = 0;
+    for y in (0..framebuffer.height).step_by(METERING_STRIDE) {
+        for x in (0..framebuffer.width).step_by(METERING_STRIDE) {
+            let index = y * framebuffer.width + x;
+            let depth = framebuffer.zbuffer[index];
+            if depth > -1.0 && depth <= 1.0 {
+                log_sum += framebuffer.hdr[index].luminance().max(MIN_METERED_LUMINANCE).ln();
+                count += 1;
+            }
+        }
+    }
+    (count > 0).then(|| (log_sum / count as f32).exp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPERATORS: [ToneMapping; 3] = [ToneMapping::Linear, ToneMapping::Reinhard, ToneMapping::Aces];
+
+    #[test]
+    fn black_stays_black() {
+        for tone_mapping in OPERATORS {
+            assert_eq!(tone_mapping.apply(Vec3::zeros()).rgb(), Vec3::zeros(), "{:?}", tone_mapping);
+        }
+    }
+
+    #[test]
+    fn curves_are_monotone() {
+        for tone_mapping in OPERATORS {
+            let mapped: Vec<f32> = (0..=400)
+                .map(|i| tone_mapping.apply(Vec3::repeat(i as f32 * 0.05)).luminance())
+                .collect();
+            assert!(mapped.windows(2).all(|pair| pair[1] >= pair[0]), "{:?}", tone_mapping);
+        }
+    }
+
+    #[test]
+    fn curves_compress_to_display_range() {
+        // Reinhard llega a 1.0 justo en el punto blanco; ACES se queda por debajo de 1.04
+        let white = ToneMapping::Reinhard.apply(Vec3::repeat(REINHARD_WHITE)).luminance();
+        assert!((white - 1.0).abs() < 1e-4, "{}", white);
+        assert!(ToneMapping::Aces.apply(Vec3::repeat(100.0)).luminance() < 1.04);
+        assert_eq!(ToneMapping::Linear.apply(Vec3::repeat(3.0)).to_hex(), 0xFFFFFF);
+    }
+
+    #[test]
+    fn reinhard_preserves_hue() {
+        let mapped = ToneMapping::Reinhard.apply(Vec3::new(2.0, 1.0, 0.5)).rgb();
+        assert!((mapped.x / mapped.y - 2.0).abs() < 1e-4 && (mapped.y / mapped.z - 2.0).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,335 @@
+// headless.rs - Render sin ventana: escribe frames del framebuffer a PNG/PPM
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::f32::consts::PI;
+use std::time::Instant;
+use nalgebra_glm::Vec3;
+use crate::camera::Camera;
+use crate::framebuffer::Framebuffer;
+use crate::hdr::{EyeAdaptation, ToneMapping};
+use crate::bloom::Bloom;
+use crate::postprocess::PostProcess;
+use crate::antialiasing::{AntiAliasing, AntiAliasingTimings, Fxaa};
+use crate::image_io::{save_image, ImageFormat};
+use crate::triangle::CullMode;
+use crate::scene::{SimulationMode, SolarSystem, DEFAULT_SCENE};
+use crate::{load_scene_assets, render_scene, RenderSettings, BACKGROUND_COLOR};
+
+pub const USAGE: &str = "\
+Uso: Proyecto3 --headless --output <ruta> [opciones]
+
+  --output <ruta>            Imagen (.png/.ppm) para una captura, o carpeta para una secuencia
+  --scene <ruta>             Archivo de escena (default assets/scenes/solar_system.toml)
+  --width <px>               Ancho del framebuffer (default 1400)
+  --height <px>              Alto del framebuffer (default 750)
+  --time <s>                 Tiempo de simulación inicial (default 0)
+  --duration <s>             Duración de la secuencia; 0 = una sola imagen (default 0)
+  --fps <n>                  Frames por segundo de la secuencia (default 30)
+  --format <png|ppm>         Formato de la secuencia (default png)
+  --camera <x,y,z>           Posición fija de la cámara (default 0,300,800)
+  --look-at <x,y,z>          Punto al que mira la cámara fija
+  --orbit-camera <r,h,T>     Cámara orbitando el origen: radio, altura y periodo en segundos
+  --no-orbits                No dibujar las órbitas
+  --orbit-width <px>         Ancho de las líneas de las órbitas (default el de la escena)
+  --nbody                    Simular con gravedad N cuerpos en vez de órbitas keplerianas
+  --headlight                Encender el foco de la nave
+  --tone-mapping <operador>  linear, reinhard o aces (default el de la escena)
+  --exposure <x>             Exposición manual, o compensación con --auto-exposure
+  --auto-exposure            Adaptar la exposición a la luminancia de la imagen
+  --no-bloom                 No aplicar el resplandor
+  --bloom-threshold <x>      Luminancia a partir de la que hay resplandor (default la de la escena)
+  --bloom-intensity <x>      Intensidad del resplandor (default la de la escena)
+  --bloom-radius <n>         Niveles de la cadena del resplandor, 1 a 8 (default los de la escena)
+  --no-post-process          No aplicar los efectos de [[post_process]] de la escena
+  --anti-aliasing <modo>     off, ordered, rotated o fxaa (default off); al final se
+                             muestra el tiempo medio por frame";
+
+// Recorrido de la cámara durante la captura
+#[derive(Clone, Copy, Debug)]
+pub enum CameraPath {
+    Fixed { position: Vec3, look_at: Option<Vec3> },
+    Orbit { radius: f32, height: f32, period: f32 },
+}
+
+impl CameraPath {
+    fn apply(&self, camera: &mut Camera, time: f32) {
+        match *self {
+            CameraPath::Fixed { position, look_at } => {
+                camera.position = position;
+                match look_at {
+                    Some(point) => camera.look_at(point),
+                    None => camera.update_vectors(),
+                }
+            }
+            CameraPath::Orbit { radius, height, period } => {
+                let angle = 2.0 * PI * time / period;
+                camera.position = Vec3::new(radius * angle.cos(), height, radius * angle.sin());
+                camera.look_at(Vec3::new(0.0, 0.0, 0.0));
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct HeadlessOptions {
+    pub width: usize,
+    pub height: usize,
+    pub start_time: f32,
+    pub duration: f32,
+    pub fps: f32,
+    pub output: PathBuf,
+    pub scene: PathBuf,
+    pub format: ImageFormat,
+    pub camera_path: CameraPath,
+    pub show_orbits: bool,
+    // Sin valor se usa el de la sección [orbits] de la escena
+    pub orbit_width: Option<f32>,
+    pub nbody: bool,
+    pub headlight: bool,
+    // Sin valor se usa lo que diga la sección [hdr] de la escena
+    pub tone_mapping: Option<ToneMapping>,
+    pub exposure: Option<f32>,
+    pub auto_exposure: bool,
+    // Igual con la sección [bloom]
+    pub bloom: bool,
+    pub bloom_threshold: Option<f32>,
+    pub bloom_intensity: Option<f32>,
+    pub bloom_radius: Option<u32>,
+    pub post_process: bool,
+    pub anti_aliasing: AntiAliasing,
+}
+
+impl HeadlessOptions {
+    pub fn from_args(args: &[String]) -> Result<Self, String> {
+        let mut options = HeadlessOptions {
+            width: 1400,
+            height: 750,
+            start_time: 0.0,
+            duration: 0.0,
+            fps: 30.0,
+            output: PathBuf::new(),
+            scene: PathBuf::from(DEFAULT_SCENE),
+            format: ImageFormat::Png,
+            camera_path: CameraPath::Fixed { position: Vec3::new(0.0, 300.0, 800.0), look_at: None },
+            show_orbits: true,
+            orbit_width: None,
+            nbody: false,
+            headlight: false,
+            tone_mapping: None,
+            exposure: None,
+            auto_exposure: false,
+            bloom: true,
+            bloom_threshold: None,
+            bloom_intensity: None,
+            bloom_radius: None,
+            post_process: true,
+            anti_aliasing: AntiAliasing::Off,
+        };
+        let mut format_given = false;
+
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            let mut value = || iter.next().ok_or_else(|| format!("falta el valor de {}", arg));
+            match arg.as_str() {
+                "--headless" => {}
+                "--no-orbits" => options.show_orbits = false,
+                "--nbody" => options.nbody = true,
+                "--headlight" => options.headlight = true,
+                "--auto-exposure" => options.auto_exposure = true,
+                "--no-bloom" => options.bloom = false,
+                "--no-post-process" => options.post_process = false,
+                "--output" => options.output = PathBuf::from(value()?),
+                "--scene" => options.scene = PathBuf::from(value()?),
+                "--width" => options.width = parse_number(arg, value()?)?,
+                "--height" => options.height = parse_number(arg, value()?)?,
+                "--time" => options.start_time = parse_number(arg, value()?)?,
+                "--duration" => options.duration = parse_number(arg, value()?)?,
+                "--fps" => options.fps = parse_number(arg, value()?)?,
+                "--exposure" => options.exposure = Some(parse_number(arg, value()?)?),
+                "--bloom-threshold" => options.bloom_threshold = Some(parse_number(arg, value()?)?),
+                "--bloom-intensity" => options.bloom_intensity = Some(parse_number(arg, value()?)?),
+                "--bloom-radius" => options.bloom_radius = Some(parse_number(arg, value()?)?),
+                "--orbit-width" => options.orbit_width = Some(parse_number(arg, value()?)?),
+                "--tone-mapping" => {
+                    let name = value()?;
+                    options.tone_mapping = Some(ToneMapping::from_name(name).ok_or_else(|| format!("tone mapping desconocido: {}", name))?);
+                }
+                "--anti-aliasing" => {
+                    let name = value()?;
+                    options.anti_aliasing = AntiAliasing::from_name(name).ok_or_else(|| format!("antialiasing desconocido: {}", name))?;
+                }
+                "--format" => {
+                    let name = value()?;
+                    options.format = ImageFormat::from_extension(Path::new(&format!("frame.{}", name)))
+                        .ok_or_else(|| format!("formato desconocido: {}", name))?;
+                    format_given = true;
+                }
+                "--camera" => {
+                    let position = parse_vec3(arg, value()?)?;
+                    let look_at = match options.camera_path {
+                        CameraPath::Fixed { look_at, .. } => look_at,
+                        CameraPath::Orbit { .. } => None,
+                    };
+                    options.camera_path = CameraPath::Fixed { position, look_at };
+                }
+                "--look-at" => {
+                    let point = parse_vec3(arg, value()?)?;
+                    let position = match options.camera_path {
+                        CameraPath::Fixed { position, .. } => position,
+                        CameraPath::Orbit { .. } => Vec3::new(0.0, 300.0, 800.0),
+                    };
+                    options.camera_path = CameraPath::Fixed { position, look_at: Some(point) };
+                }
+                "--orbit-camera" => {
+                    let orbit = parse_vec3(arg, value()?)?;
+                    if orbit.z <= 0.0 {
+                        return Err("el periodo de --orbit-camera debe ser positivo".to_string());
+                    }
+                    options.camera_path = CameraPath::Orbit { radius: orbit.x, height: orbit.y, period: orbit.z };
+                }
+                other => return Err(format!("opción desconocida: {}", other)),
+            }
+        }
+
+        if options.output.as_os_str().is_empty() {
+            return Err("falta --output".to_string());
+        }
+        if options.width == 0 || options.height == 0 {
+            return Err("la resolución debe ser mayor que cero".to_string());
+        }
+        if options.fps <= 0.0 {
+            return Err("--fps debe ser positivo".to_string());
+        }
+        if options.exposure.is_some_and(|exposure| exposure <= 0.0) {
+            return Err("--exposure debe ser positiva".to_string());
+        }
+        if options.duration <= 0.0 && !format_given {
+            // Una sola imagen: el formato sale de la extensión de la ruta
+            if let Some(format) = ImageFormat::from_extension(&options.output) {
+                options.format = format;
+            }
+        }
+
+        Ok(options)
+    }
+
+    pub fn frame_count(&self) -> usize {
+        if self.duration <= 0.0 {
+            1
+        } else {
+            ((self.duration * self.fps).round() as usize).max(1)
+        }
+    }
+
+    fn frame_path(&self, index: usize) -> PathBuf {
+        if self.duration <= 0.0 {
+            self.output.clone()
+        } else {
+            self.output.join(format!("frame_{:05}.{}", index, self.format.extension()))
+        }
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("valor inválido para {}: {}", name, value))
+}
+
+fn parse_vec3(name: &str, value: &str) -> Result<Vec3, String> {
+    let parts: Vec<f32> = value
+        .split(',')
+        .map(|part| parse_number(name, part.trim()))
+        .collect::<Result<_, _>>()?;
+    match parts.as_slice() {
+        [x, y, z] => Ok(Vec3::new(*x, *y, *z)),
+        _ => Err(format!("{} espera tres valores x,y,z: {}", name, value)),
+    }
+}
+
+pub fn run(options: &HeadlessOptions) -> Result<(), String> {
+    let mut framebuffer = Framebuffer::new(options.width, options.height);
+    framebuffer.set_background_color(BACKGROUND_COLOR);
+    framebuffer.set_anti_aliasing(options.anti_aliasing);
+    let mut fxaa = Fxaa::default();
+    let mut anti_aliasing_timings = AntiAliasingTimings::default();
+
+    let assets = load_scene_assets(options.width, options.height);
+    let settings = RenderSettings {
+        show_orbits: options.show_orbits,
+        planet_cull_mode: CullMode::Back,
+        headlight: options.headlight,
+    };
+
+    let mut camera = Camera::new(Vec3::new(0.0, 300.0, 800.0));
+    camera.update_vectors();
+
+    // Lleva la simulación al tiempo inicial pedido
+    let mut system = SolarSystem::load(&options.scene)?;
+    if options.nbody {
+        system.set_mode(SimulationMode::NBody);
+    }
+    system.update(options.start_time);
+    system.orbits.width = options.orbit_width.unwrap_or(system.orbits.width);
+    system.orbits.validate()?;
+
+    let mut hdr = system.hdr;
+    hdr.tone_mapping = options.tone_mapping.unwrap_or(hdr.tone_mapping);
+    hdr.exposure = options.exposure.unwrap_or(hdr.exposure);
+    hdr.auto_exposure |= options.auto_exposure;
+    let mut eye_adaptation = EyeAdaptation::default();
+
+    let mut bloom_settings = system.bloom;
+    bloom_settings.enabled &= options.bloom;
+    bloom_settings.threshold = options.bloom_threshold.unwrap_or(bloom_settings.threshold);
+    bloom_settings.intensity = options.bloom_intensity.unwrap_or(bloom_settings.intensity);
+    bloom_settings.radius = options.bloom_radius.unwrap_or(bloom_settings.radius);
+    bloom_settings.validate()?;
+    let mut bloom = Bloom::default();
+
+    let mut post_process = PostProcess::new(if options.post_process { system.post_process.clone() } else { Vec::new() });
+
+    if options.duration > 0.0 {
+        fs::create_dir_all(&options.output)
+            .map_err(|e| format!("no se pudo crear {}: {}", options.output.display(), e))?;
+    }
+
+    let frame_time = 1.0 / options.fps;
+    let frames = options.frame_count();
+
+    for index in 0..frames {
+        let elapsed = index as f32 * frame_time;
+        if index > 0 {
+            system.update(frame_time);
+        }
+
+        options.camera_path.apply(&mut camera, elapsed);
+        system.resolve_collisions(&mut camera);
+
+        let render_start = Instant::now();
+        render_scene(&mut framebuffer, &system, &camera, &assets, &settings, options.start_time + elapsed);
+        let render_time = render_start.elapsed();
+        let anti_aliasing_start = Instant::now();
+        framebuffer.resolve_samples();
+        let mut anti_aliasing_time = anti_aliasing_start.elapsed();
+
+        bloom.apply(&bloom_settings, &mut framebuffer);
+        // El primer frame parte ya adaptado; en una secuencia la exposición sigue a la escena
+        let exposure = eye_adaptation.update(&hdr, &framebuffer, frame_time);
+        framebuffer.resolve(hdr.tone_mapping, exposure);
+        if options.anti_aliasing == AntiAliasing::Fxaa {
+            let fxaa_start = Instant::now();
+            fxaa.apply(&mut framebuffer);
+            anti_aliasing_time += fxaa_start.elapsed();
+        }
+        anti_aliasing_timings.record(render_time, anti_aliasing_time);
+        post_process.apply(&mut framebuffer, options.start_time + elapsed);
+
+        let path = options.frame_path(index);
+        save_image(&framebuffer, &path, options.format)
+            .map_err(|e| format!("no se pudo escribir {}: {}", path.display(), e))?;
+        println!("🖼️  Frame {}/{} -> {}", index + 1, frames, path.display());
+    }
+    println!("⏱️  {} (media de {} frames)", anti_aliasing_timings.summary(options.anti_aliasing), frames);
+
+    Ok(())
+}
@@ -0,0 +1,91 @@
+// image_io.rs - Escritura del framebuffer a archivos de imagen (PNG / PPM)
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use crate::framebuffer::Framebuffer;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ImageFormat {
+    Png,
+    Ppm,
+}
+
+impl ImageFormat {
+    pub fn from_extension(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "png" => Some(ImageFormat::Png),
+            "ppm" => Some(ImageFormat::Ppm),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Ppm => "ppm",
+        }
+    }
+}
+
+// Convierte el buffer 0xRRGGBB a bytes RGB consecutivos
+fn to_rgb_bytes(framebuffer: &Framebuffer) -> Vec<u8> {
+    let mut data = Vec::with_capacity(framebuffer.buffer.len() * 3);
+    for &pixel in &framebuffer.buffer {
+        data.push(((pixel >> 16) & 0xFF) as u8);
+        data.push(((pixel >> 8) & 0xFF) as u8);
+        data.push((pixel & 0xFF) as u8);
+    }
+    data
+}
+
+pub fn save_image(framebuffer: &Framebuffer, path: &Path, format: ImageFormat) -> io::Result<()> {
+    match format {
+        ImageFormat::Png => save_png(framebuffer, path),
+        ImageFormat::Ppm => save_ppm(framebuffer, path),
+    }
+}
+
+pub fn save_png(framebuffer: &Framebuffer, path: &Path) -> io::Result<()> {
+    let writer = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(writer, framebuffer.width as u32, framebuffer.height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header().map_err(io::Error::other)?;
+    writer.write_image_data(&to_rgb_bytes(framebuffer)).map_err(io::Error::other)?;
+    Ok(())
+}
+
+// Lee un PNG RGB/RGBA de 8 bits como buffer 0xRRGGBB (referencias de los tests)
+#[cfg(test)]
+pub fn load_png(path: &Path) -> io::Result<(usize, usize, Vec<u32>)> {
+    let mut decoder = png::Decoder::new(File::open(path)?);
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info().map_err(io::Error::other)?;
+    let mut data = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut data).map_err(io::Error::other)?;
+
+    let channels = match info.color_type {
+        png::ColorType::Rgb => 3,
+        png::ColorType::Rgba => 4,
+        other => {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("PNG con formato no soportado: {:?}", other)));
+        }
+    };
+
+    let pixels = data[..info.buffer_size()]
+        .chunks(channels)
+        .map(|p| ((p[0] as u32) << 16) | ((p[1] as u32) << 8) | (p[2] as u32))
+        .collect();
+
+    Ok((info.width as usize, info.height as usize, pixels))
+}
+
+// PPM binario (P6): sin dependencias, útil para tuberías hacia ffmpeg
+pub fn save_ppm(framebuffer: &Framebuffer, path: &Path) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write!(writer, "P6\n{} {}\n255\n", framebuffer.width, framebuffer.height)?;
+    writer.write_all(&to_rgb_bytes(framebuffer))?;
+    writer.flush()
+}
@@ -0,0 +1,200 @@
+// light.rs - Iluminación de la escena: luces puntuales, direccionales y focos, y la luz ambiente
+//
+// Los cuerpos emisivos (las estrellas) son luces puntuales en su posición de mundo. El
+// archivo de escena puede añadir más luces en [[lights]], y la nave lleva un foco.
+
+use nalgebra_glm::Vec3;
+use serde::Deserialize;
+use crate::shader::smoothstep;
+use crate::shadow::{light_visibility, Occluder};
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LightingSettings {
+    // Luz que recibe el lado nocturno (fracción del albedo)
+    pub ambient: f32,
+    // Distancia a la que la luz de una estrella cae a la mitad (si no define la suya)
+    pub falloff_distance: f32,
+}
+
+impl Default for LightingSettings {
+    fn default() -> Self {
+        LightingSettings {
+            ambient: 0.05,
+            falloff_distance: 1500.0,
+        }
+    }
+}
+
+// Luz que emite un cuerpo (las estrellas): es una luz puntual en su centro y su superficie
+// no recibe iluminación
+#[derive(Clone, Copy, Debug)]
+pub struct Emission {
+    pub color: Vec3,
+    pub intensity: f32,
+    pub falloff_distance: f32,
+}
+
+impl Emission {
+    // `radius` es el del cuerpo: da el tamaño de la penumbra de sus eclipses
+    pub fn light_at(&self, position: Vec3, radius: f32) -> Light {
+        Light {
+            kind: LightKind::Point { position, falloff_distance: self.falloff_distance, radius },
+            color: self.color,
+            intensity: self.intensity,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum LightKind {
+    // Con `radius` > 0 la luz es una esfera y sus sombras tienen penumbra
+    Point { position: Vec3, falloff_distance: f32, radius: f32 },
+    // `direction` es hacia donde viaja la luz
+    Directional { direction: Vec3 },
+    // Cono con intensidad completa hasta `inner_angle` y nula desde `outer_angle` (radianes)
+    Spot { position: Vec3, direction: Vec3, falloff_distance: f32, inner_angle: f32, outer_angle: f32 },
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Light {
+    pub kind: LightKind,
+    pub color: Vec3, // RGB lineal en [0, 1]
+    pub intensity: f32,
+}
+
+impl Light {
+    // Dirección hacia la luz (normalizada) y luz de color que llega a `point`
+    pub fn incident(&self, point: &Vec3) -> (Vec3, Vec3) {
+        let (light_dir, attenuation) = match self.kind {
+            LightKind::Point { position, falloff_distance, .. } => {
+                let (light_dir, distance) = direction_to(&position, point);
+                (light_dir, distance_falloff(distance, falloff_distance))
+            }
+            LightKind::Directional { direction } => (-direction.normalize(), 1.0),
+            LightKind::Spot { position, direction, falloff_distance, inner_angle, outer_angle } => {
+                let (light_dir, distance) = direction_to(&position, point);
+                let cos_angle = (-light_dir).dot(&direction.normalize());
+                let cone = smoothstep(outer_angle.cos(), inner_angle.cos(), cos_angle);
+                (light_dir, distance_falloff(distance, falloff_distance) * cone)
+            }
+        };
+        (light_dir, self.color * (self.intensity * attenuation))
+    }
+
+    // Fracción de la luz que no tapan los cuerpos de la escena
+    pub fn visibility(&self, point: &Vec3, light_dir: &Vec3, occluders: &[Occluder]) -> f32 {
+        let (distance, radius) = match self.kind {
+            LightKind::Point { position, radius, .. } => ((position - point).magnitude(), radius),
+            LightKind::Spot { position, .. } => ((position - point).magnitude(), 0.0),
+            LightKind::Directional { .. } => (f32::INFINITY, 0.0),
+        };
+        light_visibility(point, light_dir, distance, radius, occluders)
+    }
+}
+
+fn direction_to(position: &Vec3, point: &Vec3) -> (Vec3, f32) {
+    let offset = position - point;
+    let distance = offset.magnitude();
+    (offset / distance.max(1e-6), distance)
+}
+
+// Cae a la mitad en `falloff_distance`
+fn distance_falloff(distance: f32, falloff_distance: f32) -> f32 {
+    let falloff_sq = falloff_distance * falloff_distance;
+    falloff_sq / (falloff_sq + distance * distance)
+}
+
+// Formato de [[lights]] en el archivo de escena
+#[derive(Deserialize)]
+#[serde(tag = "type", deny_unknown_fields)]
+pub enum LightDescription {
+    Point {
+        position: [f32; 3],
+        #[serde(default = "white")]
+        color: [f32; 3],
+        #[serde(default = "one")]
+        intensity: f32,
+        #[serde(default)]
+        falloff_distance: Option<f32>,
+        #[serde(default)]
+        radius: f32,
+    },
+    Directional {
+        direction: [f32; 3],
+        #[serde(default = "white")]
+        color: [f32; 3],
+        #[serde(default = "one")]
+        intensity: f32,
+    },
+    Spot {
+        position: [f32; 3],
+        direction: [f32; 3],
+        #[serde(default = "white")]
+        color: [f32; 3],
+        #[serde(default = "one")]
+        intensity: f32,
+        #[serde(default)]
+        falloff_distance: Option<f32>,
+        inner_angle: f32,
+        outer_angle: f32,
+    },
+}
+
+fn white() -> [f32; 3] {
+    [1.0, 1.0, 1.0]
+}
+
+fn one() -> f32 {
+    1.0
+}
+
+impl LightDescription {
+    pub fn to_light(&self, settings: &LightingSettings) -> Result<Light, String> {
+        let light = match *self {
+            LightDescription::Point { position, color, intensity, falloff_distance, radius } => {
+                if radius < 0.0 {
+                    return Err("el radio de una luz puntual no puede ser negativo".to_string());
+                }
+                Light {
+                    kind: LightKind::Point {
+                        position: Vec3::from(position),
+                        falloff_distance: falloff_distance.unwrap_or(settings.falloff_distance),
+                        radius,
+                    },
+                    color: Vec3::from(color),
+                    intensity,
+                }
+            }
+            LightDescription::Directional { direction, color, intensity } => {
+                if Vec3::from(direction).magnitude_squared() == 0.0 {
+                    return Err("la dirección de una luz direccional no puede ser nula".to_string());
+                }
+                Light { kind: LightKind::Directional { direction: Vec3::from(direction) }, color: Vec3::from(color), intensity }
+            }
+            LightDescription::Spot { position, direction, color, intensity, falloff_distance, inner_angle, outer_angle } => {
+                if Vec3::from(direction).magnitude_squared() == 0.0 {
+                    return Err("la dirección de un foco no puede ser nula".to_string());
+                }
+                if !(0.0..outer_angle).contains(&inner_angle) {
+                    return Err("un foco necesita 0 <= inner_angle < outer_angle".to_string());
+                }
+                Light {
+                    kind: LightKind::Spot {
+                        position: Vec3::from(position),
+                        direction: Vec3::from(direction),
+                        falloff_distance: falloff_distance.unwrap_or(settings.falloff_distance),
+                        inner_angle,
+                        outer_angle,
+                    },
+                    color: Vec3::from(color),
+                    intensity,
+                }
+            }
+        };
+        if light.intensity < 0.0 || light.color.iter().any(|&channel| channel < 0.0) {
+            return Err("el color y la intensidad de una luz no pueden ser negativos".to_string());
+        }
+        Ok(light)
+    }
+}
@@ -1,42 +1,106 @@
-use crate::fragment::Fragment;
-use crate::vertex::Vertex;
-use crate::color::Color;
+// line.rs - Líneas 3D (órbitas): pasan por la misma vista y proyección que los triángulos,
+// se recortan contra el frustum y se rasterizan con antialiasing de Xiaolin Wu
+//
+// La línea no escribe profundidad: se mezcla sobre lo ya dibujado solo donde está delante,
+// así que los cuerpos la tapan pero ella no tapa lo que se dibuje después.
 
-pub fn line(a: &Vertex, b: &Vertex) -> Vec<Fragment> {
-    let mut fragments = Vec::new();
+use nalgebra_glm::{Mat4, Vec3, Vec4};
+use serde::Deserialize;
+use crate::clipping::{clip_line, to_screen};
+use crate::color::{BlendMode, Color};
+use crate::framebuffer::Framebuffer;
 
-    let start = a.transformed_position;
-    let end = b.transformed_position;
+pub const MAX_WIDTH: f32 = 8.0;
 
-    let mut x0 = start.x as i32;
-    let mut y0 = start.y as i32;
-    let x1 = end.x as i32;
-    let y1 = end.y as i32;
+// Sección [orbits] del archivo de escena; el color de cada órbita es el `color` de su cuerpo
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OrbitSettings {
+    // Ancho de las líneas en píxeles
+    pub width: f32,
+    pub opacity: f32,
+}
 
-    let dx = (x1 - x0).abs();
-    let dy = (y1 - y0).abs();
+impl Default for OrbitSettings {
+    fn default() -> Self {
+        OrbitSettings {
+            width: 1.0,
+            opacity: 0.8,
+        }
+    }
+}
 
-    let sx = if x0 < x1 { 1 } else { -1 };
-    let sy = if y0 < y1 { 1 } else { -1 };
+impl OrbitSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.width <= 0.0 || self.width > MAX_WIDTH {
+            return Err(format!("orbits.width debe estar entre 0 y {}", MAX_WIDTH));
+        }
+        if !(0.0..=1.0).contains(&self.opacity) {
+            return Err("orbits.opacity debe estar en [0, 1]".to_string());
+        }
+        Ok(())
+    }
 
-    let mut err = if dx > dy { dx / 2 } else { -dy / 2 };
+    pub fn line_style(&self, color: u32) -> LineStyle {
+        LineStyle { color: Color::from_hex(color).fade(self.opacity), width: self.width }
+    }
+}
 
-    loop {
-        let z = start.z + (end.z - start.z) * (x0 - start.x as i32) as f32 / (end.x - start.x) as f32;
-        fragments.push(Fragment::new(x0 as f32, y0 as f32, Color::new(255, 255, 255), z));
+#[derive(Clone, Copy, Debug)]
+pub struct LineStyle {
+    // Color lineal; su opacidad se multiplica por la cobertura de cada píxel
+    pub color: Color,
+    pub width: f32,
+}
 
-        if x0 == x1 && y0 == y1 { break; }
+// Segmento entre dos puntos de mundo. `view_projection` lleva a clip space como en el
+// vertex shader y `viewport_matrix` de NDC a píxeles
+pub fn draw_line(framebuffer: &mut Framebuffer, view_projection: &Mat4, viewport_matrix: &Mat4, start: Vec3, end: Vec3, style: LineStyle) {
+    let to_clip = |p: Vec3| view_projection * Vec4::new(p.x, p.y, p.z, 1.0);
+    let Some((a, b)) = clip_line(to_clip(start), to_clip(end)) else {
+        return;
+    };
+    rasterize_line(framebuffer, to_screen(&a, viewport_matrix), to_screen(&b, viewport_matrix), style);
+}
 
-        let e2 = err;
-        if e2 > -dx {
-            err -= dy;
-            x0 += sx;
-        }
-        if e2 < dy {
-            err += dx;
-            y0 += sy;
-        }
+// Wu generalizado a cualquier ancho: se avanza un píxel por paso sobre el eje mayor y en el
+// eje menor cada píxel recibe la fracción que cubre de la franja [centro - w/2, centro + w/2].
+// Con ancho 1 son los dos píxeles de Wu con intensidades complementarias.
+fn rasterize_line(framebuffer: &mut Framebuffer, a: Vec3, b: Vec3, style: LineStyle) {
+    // Con la línea empinada se recorre y como eje mayor
+    let steep = (b.y - a.y).abs() > (b.x - a.x).abs();
+    let swap_axes = |p: Vec3| if steep { Vec3::new(p.y, p.x, p.z) } else { p };
+    let (mut a, mut b) = (swap_axes(a), swap_axes(b));
+    if a.x > b.x {
+        std::mem::swap(&mut a, &mut b);
+    }
+
+    let length = b.x - a.x;
+    if length <= 0.0 {
+        return;
     }
+    let gradient = (b.y - a.y) / length;
+    let depth_gradient = (b.z - a.z) / length;
+    // Medida sobre el eje menor, la franja de una línea inclinada es más alta que su ancho
+    let half_height = 0.5 * style.width * (1.0 + gradient * gradient).sqrt();
 
-    fragments
-}
\ No newline at end of file
+    // Cada paso es del segmento si el centro del píxel cae en [a.x, b.x): los segmentos
+    // consecutivos de una órbita no pintan dos veces el píxel que comparten
+    let first = (a.x - 0.5).ceil() as i64;
+    let last = (b.x - 0.5).ceil() as i64;
+    for major in first..last {
+        let center = major as f32 + 0.5;
+        let minor = a.y + gradient * (center - a.x);
+        // La profundidad en pantalla es lineal a lo largo de la línea proyectada
+        let depth = a.z + depth_gradient * (center - a.x);
+        let (top, bottom) = (minor - half_height, minor + half_height);
+
+        for row in top.floor() as i64..bottom.ceil() as i64 {
+            let coverage = (bottom.min(row as f32 + 1.0) - top.max(row as f32)).clamp(0.0, 1.0);
+            let (x, y) = if steep { (row, major) } else { (major, row) };
+            if coverage > 0.0 && x >= 0 && y >= 0 {
+                framebuffer.blend(x as usize, y as usize, style.color.fade(coverage), depth, BlendMode::Alpha);
+            }
+        }
+    }
+}
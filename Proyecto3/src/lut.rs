@@ -0,0 +1,155 @@
+// lut.rs - Tablas de color 3D en formato .cube (Adobe / Resolve) para el color grading
+//
+// La tabla se indexa con el color de pantalla (sRGB en [0, 1]) y devuelve el color
+// corregido; entre las muestras se interpola trilinealmente.
+
+use std::fs;
+use std::path::Path;
+use nalgebra_glm::Vec3;
+
+// Tamaño máximo de las LUTs .cube en la práctica (y el que acepta Resolve)
+const MAX_SIZE: usize = 256;
+
+pub struct Lut {
+    size: usize,
+    domain_min: Vec3,
+    domain_max: Vec3,
+    // size³ entradas con el rojo variando más rápido, como en el archivo
+    table: Vec<Vec3>,
+}
+
+impl Lut {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let source = fs::read_to_string(path)
+            .map_err(|e| format!("no se pudo leer la LUT {}: {}", path.display(), e))?;
+        Self::parse(&source).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut size = None;
+        let mut domain_min = Vec3::zeros();
+        let mut domain_max = Vec3::repeat(1.0);
+        let mut table = Vec::new();
+
+        for (number, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |message: &str| format!("línea {}: {}", number + 1, message);
+            let mut words = line.split_whitespace();
+            let keyword = words.next().unwrap_or_default();
+            match keyword {
+                "TITLE" => {}
+                "LUT_1D_SIZE" => return Err(error("solo se soportan LUTs 3D")),
+                "LUT_3D_SIZE" => {
+                    let value: usize = words.next().and_then(|w| w.parse().ok()).ok_or_else(|| error("LUT_3D_SIZE inválido"))?;
+                    if !(2..=MAX_SIZE).contains(&value) {
+                        return Err(error(&format!("LUT_3D_SIZE debe estar entre 2 y {}", MAX_SIZE)));
+                    }
+                    size = Some(value);
+                }
+                "DOMAIN_MIN" => domain_min = parse_triplet(words).ok_or_else(|| error("DOMAIN_MIN inválido"))?,
+                "DOMAIN_MAX" => domain_max = parse_triplet(words).ok_or_else(|| error("DOMAIN_MAX inválido"))?,
+                _ => {
+                    let entry = parse_triplet(line.split_whitespace()).ok_or_else(|| error("entrada inválida"))?;
+                    table.push(entry);
+                }
+            }
+        }
+
+        let size = size.ok_or("falta LUT_3D_SIZE")?;
+        if table.len() != size * size * size {
+            return Err(format!("se esperaban {} entradas y hay {}", size * size * size, table.len()));
+        }
+        if (0..3).any(|i| domain_max[i] <= domain_min[i]) {
+            return Err("DOMAIN_MAX debe ser mayor que DOMAIN_MIN".to_string());
+        }
+        Ok(Lut { size, domain_min, domain_max, table })
+    }
+
+    // Color corregido para `color` (sRGB en [0, 1])
+    pub fn sample(&self, color: Vec3) -> Vec3 {
+        let last = (self.size - 1) as f32;
+        let coords: [f32; 3] = std::array::from_fn(|i| {
+            let t = (color[i] - self.domain_min[i]) / (self.domain_max[i] - self.domain_min[i]);
+            t.clamp(0.0, 1.0) * last
+        });
+        let base = coords.map(|c| (c.floor() as usize).min(self.size - 2));
+        let [fr, fg, fb]: [f32; 3] = std::array::from_fn(|i| coords[i] - base[i] as f32);
+        let [r, g, b] = base;
+
+        let lerp = |a: Vec3, b: Vec3, t: f32| a + (b - a) * t;
+        let along_r = |g: usize, b: usize| lerp(self.entry(r, g, b), self.entry(r + 1, g, b), fr);
+        let front = lerp(along_r(g, b), along_r(g + 1, b), fg);
+        let back = lerp(along_r(g, b + 1), along_r(g + 1, b + 1), fg);
+        lerp(front, back, fb)
+    }
+
+    fn entry(&self, r: usize, g: usize, b: usize) -> Vec3 {
+        self.table[(b * self.size + g) * self.size + r]
+    }
+}
+
+fn parse_triplet<'a>(mut words: impl Iterator<Item = &'a str>) -> Option<Vec3> {
+    let mut next = || words.next()?.parse::<f32>().ok();
+    let triplet = Vec3::new(next()?, next()?, next()?);
+    words.next().is_none().then_some(triplet)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // LUT identidad de tamaño 2, con la cabecera `header` antes de las entradas
+    fn identity_source(header: &str) -> String {
+        let mut source = format!("TITLE \"identidad\"\n{}\n", header);
+        for i in 0..8 {
+            source.push_str(&format!("{} {} {}\n", i & 1, (i >> 1) & 1, (i >> 2) & 1));
+        }
+        source
+    }
+
+    fn parse_error(source: &str) -> String {
+        Lut::parse(source).err().expect("la LUT debería ser rechazada")
+    }
+
+    #[test]
+    fn identity_lut_returns_its_input() {
+        let lut = Lut::parse(&identity_source("# comentario\nLUT_3D_SIZE 2")).unwrap();
+        for color in [Vec3::new(0.2, 0.5, 0.9), Vec3::new(1.0, 0.0, 0.3), Vec3::zeros()] {
+            assert!((lut.sample(color) - color).magnitude() < 1e-6, "{:?}", color);
+        }
+        // Fuera del dominio se recorta
+        assert_eq!(lut.sample(Vec3::new(2.0, -1.0, 0.5)), Vec3::new(1.0, 0.0, 0.5));
+    }
+
+    #[test]
+    fn domain_rescales_the_input() {
+        let lut = Lut::parse(&identity_source("LUT_3D_SIZE 2\nDOMAIN_MIN 0 0 0\nDOMAIN_MAX 2 2 2")).unwrap();
+        assert!((lut.sample(Vec3::new(1.0, 0.5, 2.0)) - Vec3::new(0.5, 0.25, 1.0)).magnitude() < 1e-6);
+    }
+
+    #[test]
+    fn rejects_bad_sizes() {
+        assert!(parse_error(&identity_source("LUT_3D_SIZE 1")).contains("LUT_3D_SIZE"));
+        assert!(parse_error(&identity_source("LUT_3D_SIZE 3000000")).contains("LUT_3D_SIZE"));
+        assert!(parse_error(&identity_source("LUT_3D_SIZE dos")).contains("LUT_3D_SIZE"));
+        assert!(parse_error(&identity_source("")).contains("falta LUT_3D_SIZE"));
+        assert!(parse_error("LUT_1D_SIZE 16\n").contains("3D"));
+    }
+
+    #[test]
+    fn rejects_wrong_entry_count() {
+        let error = parse_error(&identity_source("LUT_3D_SIZE 3"));
+        assert!(error.contains("27") && error.contains("8"), "{}", error);
+    }
+
+    #[test]
+    fn rejects_inverted_domain_and_bad_entries() {
+        let error = parse_error(&identity_source("LUT_3D_SIZE 2\nDOMAIN_MIN 0 1 0\nDOMAIN_MAX 1 0 1"));
+        assert!(error.contains("DOMAIN_MAX"), "{}", error);
+        assert!(parse_error(&identity_source("LUT_3D_SIZE 2\n0.5 0.5")).contains("línea"));
+        assert!(parse_error(&identity_source("LUT_3D_SIZE 2\n0.5 0.5 0.5 0.5")).contains("línea"));
+    }
+}
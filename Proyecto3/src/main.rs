@@ -1,7 +1,9 @@
-use nalgebra_glm::{Vec3, Mat4};
+use nalgebra_glm::{Vec3, Mat3, Mat4, mat4_to_mat3};
 use minifb::{Key, Window, WindowOptions};
 use std::time::{Duration, Instant};
 use std::f32::consts::PI;
+use std::path::PathBuf;
+use std::sync::Arc;
 
 mod framebuffer;
 mod triangle;
@@ -14,29 +16,86 @@ mod shaders;
 mod shader;
 mod camera;
 mod skybox;
+mod clipping;
+mod rasterizer;
+mod image_io;
+mod headless;
+mod scene;
+mod orbit;
+mod physics;
+mod texture;
+mod light;
+mod shadow;
+mod ring;
+mod atmosphere;
+mod clouds;
+mod hdr;
+mod bloom;
+mod lut;
+mod postprocess;
+mod antialiasing;
+
+#[cfg(test)]
+mod golden_tests;
 
 use framebuffer::Framebuffer;
+use color::BlendMode;
 use vertex::Vertex;
 use obj::Obj;
-use triangle::{triangle, ShaderType}; 
+use triangle::{is_culled, CullMode, RenderState, ShaderParams, ShaderType};
 use shaders::vertex_shader;
 use camera::Camera;
 use skybox::Skybox;
-
+use clipping::clip_triangle;
+use rasterizer::rasterize;
+use headless::HeadlessOptions;
+use scene::{BodyModel, SimulationMode, SolarSystem};
+use orbit::OrbitalElements;
+use texture::{NormalMap, SurfaceTexture};
+use light::{Light, LightKind};
+use shadow::Occluder;
+use atmosphere::Atmosphere;
+use clouds::CloudLayer;
+use hdr::EyeAdaptation;
+use bloom::{Bloom, MAX_RADIUS};
+use postprocess::PostProcess;
+use antialiasing::{AntiAliasing, AntiAliasingTimings, Fxaa};
+use line::{draw_line, LineStyle};
+
+const BACKGROUND_COLOR: u32 = 0x000008;
+
+const WARP_KEYS: [Key; 9] = [
+    Key::Key1, Key::Key2, Key::Key3, Key::Key4, Key::Key5,
+    Key::Key6, Key::Key7, Key::Key8, Key::Key9,
+];
+
+// Encienden/apagan los efectos de post-proceso en el orden de la escena
+const POST_PROCESS_KEYS: [Key; 9] = [
+    Key::F1, Key::F2, Key::F3, Key::F4, Key::F5,
+    Key::F6, Key::F7, Key::F8, Key::F9,
+];
+
+#[derive(Clone)]
 pub struct Uniforms {
     model_matrix: Mat4,
-}
-
-struct CelestialBody {
-    orbital_radius: f32,
-    orbital_angle: f32,
-    orbital_speed: f32,
-    scale: f32,
-    rotation: Vec3,
-    shader_type: ShaderType,
-    rotation_speed: f32,
-    name: &'static str,
-    color: u32, // Para las órbitas
+    view_matrix: Mat4,
+    projection_matrix: Mat4,
+    viewport_matrix: Mat4,
+    normal_matrix: Mat3,
+    time: f32,
+    shader_params: ShaderParams,
+    texture: Option<SurfaceTexture>,
+    normal_map: Option<NormalMap>,
+    // Solo para ShaderType::Atmosphere
+    atmosphere: Option<Atmosphere>,
+    // La capa de nubes del cuerpo: la dibuja ShaderType::Clouds y hace sombra en el suelo
+    clouds: Option<CloudLayer>,
+    camera_position: Vec3,
+    lights: Arc<[Light]>,
+    // Cuerpos que pueden tapar las luces (eclipses)
+    occluders: Arc<[Occluder]>,
+    ambient: f32,
+    emissive: bool,
 }
 
 // #[derive(Clone, Copy, PartialEq)]
@@ -88,93 +147,61 @@ fn create_model_matrix(translation: Vec3, scale: f32, rotation: Vec3) -> Mat4 {
     transform_matrix * rotation_matrix
 }
 
-fn render(framebuffer: &mut Framebuffer, uniforms: &Uniforms, vertex_array: &[Vertex], shader_type: ShaderType, time: f32) {
+fn create_viewport_matrix(width: f32, height: f32) -> Mat4 {
+    // NDC [-1, 1] -> píxeles, con y hacia abajo
+    Mat4::new(
+        width / 2.0, 0.0, 0.0, width / 2.0,
+        0.0, -height / 2.0, 0.0, height / 2.0,
+        0.0, 0.0, 1.0, 0.0,
+        0.0, 0.0, 0.0, 1.0,
+    )
+}
+
+// Lleva normales del modelo a coordenadas de mundo, donde se calcula la iluminación:
+// inversa transpuesta de la matriz de modelo
+fn create_normal_matrix(model_matrix: &Mat4) -> Mat3 {
+    mat4_to_mat3(model_matrix)
+        .try_inverse()
+        .unwrap_or(Mat3::identity())
+        .transpose()
+}
+
+fn render(framebuffer: &mut Framebuffer, uniforms: &Uniforms, vertex_array: &[Vertex], shader_type: Option<ShaderType>, state: RenderState) {
     let mut transformed_vertices = Vec::with_capacity(vertex_array.len());
     for vertex in vertex_array {
         let transformed = vertex_shader(vertex, uniforms);
         transformed_vertices.push(transformed);
     }
 
+    // Recorte contra el frustum entre el vertex shader y la rasterización
     let mut triangles = Vec::new();
     for i in (0..transformed_vertices.len()).step_by(3) {
         if i + 2 < transformed_vertices.len() {
-            triangles.push([
-                transformed_vertices[i].clone(),
-                transformed_vertices[i + 1].clone(),
-                transformed_vertices[i + 2].clone(),
-            ]);
+            triangles.extend(clip_triangle(
+                &transformed_vertices[i],
+                &transformed_vertices[i + 1],
+                &transformed_vertices[i + 2],
+                &uniforms.viewport_matrix,
+            ));
         }
     }
 
-    let mut fragments = Vec::new();
-    for tri in &triangles {
-        fragments.extend(triangle(&tri[0], &tri[1], &tri[2], shader_type, time));
-    }
+    // Back/front-face culling antes de rasterizar
+    triangles.retain(|tri| !is_culled(&tri[0], &tri[1], &tri[2], state.cull_mode));
 
-    for fragment in fragments {
-        let x = fragment.position.x as usize;
-        let y = fragment.position.y as usize;
-        if x < framebuffer.width && y < framebuffer.height {
-            let color = fragment.color.to_hex();
-            framebuffer.set_current_color(color);
-            framebuffer.point(x, y, fragment.depth);
-        }
-    }
+    rasterize(framebuffer, &triangles, shader_type, uniforms, state);
 }
 
-fn draw_orbit(framebuffer: &mut Framebuffer, center: Vec3, radius: f32, color: u32, camera: &Camera, window_width: f32, window_height: f32) {
-    let segments = 100;
+fn draw_orbit(framebuffer: &mut Framebuffer, center: Vec3, orbit: &OrbitalElements, style: LineStyle, view_projection: &Mat4, viewport_matrix: &Mat4) {
+    // Muestreo uniforme en anomalía excéntrica: reparte mejor los segmentos en órbitas excéntricas
+    let segments = 128;
     for i in 0..segments {
         let angle1 = (i as f32 / segments as f32) * 2.0 * PI;
         let angle2 = ((i + 1) as f32 / segments as f32) * 2.0 * PI;
 
-        let p1 = Vec3::new(
-            center.x + radius * angle1.cos(),
-            center.y,
-            center.z + radius * angle1.sin(),
-        );
-
-        let p2 = Vec3::new(
-            center.x + radius * angle2.cos(),
-            center.y,
-            center.z + radius * angle2.sin(),
-        );
-
-        let screen1 = camera.get_screen_position(p1, window_width, window_height);
-        let screen2 = camera.get_screen_position(p2, window_width, window_height);
-
-        if screen1.z > 0.0 && screen2.z > 0.0 {
-            draw_line(framebuffer, screen1.x as i32, screen1.y as i32, screen2.x as i32, screen2.y as i32, color);
-        }
-    }
-}
-
-fn draw_line(framebuffer: &mut Framebuffer, x1: i32, y1: i32, x2: i32, y2: i32, color: u32) {
-    let dx = (x2 - x1).abs();
-    let dy = -(y2 - y1).abs();
-    let sx = if x1 < x2 { 1 } else { -1 };
-    let sy = if y1 < y2 { 1 } else { -1 };
-    let mut err = dx + dy;
-    let mut x = x1;
-    let mut y = y1;
-
-    loop {
-        if x >= 0 && y >= 0 && (x as usize) < framebuffer.width && (y as usize) < framebuffer.height {
-            framebuffer.set_current_color(color);
-            framebuffer.point(x as usize, y as usize, -1.0);
-        }
-        if x == x2 && y == y2 {
-            break;
-        }
-        let e2 = 2 * err;
-        if e2 >= dy {
-            err += dy;
-            x += sx;
-        }
-        if e2 <= dx {
-            err += dx;
-            y += sy;
-        }
+        let p1 = center + orbit.position_at_eccentric_anomaly(angle1);
+        let p2 = center + orbit.position_at_eccentric_anomaly(angle2);
+        draw_line(framebuffer, view_projection, viewport_matrix, p1, p2, style);
     }
 }
 
@@ -298,22 +325,67 @@ fn draw_char(framebuffer: &mut Framebuffer, x: usize, y: usize, c: char, color:
     let index = c as usize - 32;
     let bitmap = FONT[index];
     
-    framebuffer.set_current_color(color);
-    
     for (row, bits) in bitmap.iter().enumerate() {
         for col in 0..8 {
             if bits & (1 << col) != 0 {
-                let px = x + col;
-                let py = y + row;
-                if px < framebuffer.width && py < framebuffer.height {
-                    framebuffer.point(px, py, -2.0); // Depth negativo para estar al frente
-                }
+                // Encima de la imagen ya resuelta, sin tone mapping
+                framebuffer.overlay(x + col, y + row, color);
             }
         }
     }
 }
 
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    if args.iter().any(|arg| arg == "--headless") {
+        let options = HeadlessOptions::from_args(&args).unwrap_or_else(|error| {
+            eprintln!("Error: {}", error);
+            eprintln!();
+            eprintln!("{}", headless::USAGE);
+            std::process::exit(1);
+        });
+        if let Err(error) = headless::run(&options) {
+            eprintln!("Error: {}", error);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let scene_path = scene_path_from_args(&args);
+    let system = SolarSystem::load(&scene_path).unwrap_or_else(|error| {
+        eprintln!("Error: {}", error);
+        std::process::exit(1);
+    });
+    let anti_aliasing = anti_aliasing_from_args(&args).unwrap_or_else(|error| {
+        eprintln!("Error: {}", error);
+        std::process::exit(1);
+    });
+
+    run_window(system, anti_aliasing);
+}
+
+// Ruta de la escena pasada con `--scene`, o la escena por defecto
+fn scene_path_from_args(args: &[String]) -> PathBuf {
+    args.iter()
+        .position(|arg| arg == "--scene")
+        .and_then(|i| args.get(i + 1))
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(scene::DEFAULT_SCENE))
+}
+
+// Modo pasado con `--anti-aliasing`, o sin antialiasing
+fn anti_aliasing_from_args(args: &[String]) -> Result<AntiAliasing, String> {
+    match args.iter().position(|arg| arg == "--anti-aliasing") {
+        None => Ok(AntiAliasing::Off),
+        Some(i) => {
+            let name = args.get(i + 1).ok_or("falta el valor de --anti-aliasing")?;
+            AntiAliasing::from_name(name).ok_or_else(|| format!("antialiasing desconocido: {} (off, ordered, rotated o fxaa)", name))
+        }
+    }
+}
+
+fn run_window(mut system: SolarSystem, anti_aliasing: AntiAliasing) {
     let window_width = 1400;
     let window_height = 750;
     let framebuffer_width = 1400;
@@ -321,6 +393,9 @@ fn main() {
     let frame_delay = Duration::from_millis(16);
 
     let mut framebuffer = Framebuffer::new(framebuffer_width, framebuffer_height);
+    framebuffer.set_anti_aliasing(anti_aliasing);
+    let mut fxaa = Fxaa::default();
+    let mut anti_aliasing_timings = AntiAliasingTimings::default();
     let mut window = Window::new(
         "Sistema Solar 3D - Proyecto Final",
         window_width,
@@ -332,17 +407,9 @@ fn main() {
     window.set_position(100, 50);
     window.update();
 
-    framebuffer.set_background_color(0x000008);
+    framebuffer.set_background_color(BACKGROUND_COLOR);
 
-    // Cargar modelos
-    let sphere_obj = Obj::load("../assets/models/sphere.obj").expect("Failed to load sphere.obj");
-    let sphere_vertices = sphere_obj.get_vertex_array();
-
-    let spaceship_obj = Obj::load("../assets/models/spaceship.obj").expect("Failed to load spaceship.obj");
-    let spaceship_vertices = spaceship_obj.get_vertex_array();
-
-    // Crear skybox con 1500 estrellas
-    let skybox = Skybox::new(1500, framebuffer_width, framebuffer_height);
+    let assets = load_scene_assets(framebuffer_width, framebuffer_height);
 
     // Inicializar cámara
     let mut camera = Camera::new(Vec3::new(0.0, 300.0, 800.0));
@@ -351,99 +418,25 @@ fn main() {
     let mut time = 0.0f32;
     let mut last_frame = Instant::now();
 
-    // Centro del sistema solar
-    let sun_center = Vec3::new(0.0, 0.0, 0.0);
-
-    // Definir cuerpos celestes
-    let mut bodies = vec![
-        CelestialBody {
-            orbital_radius: 0.0,
-            orbital_angle: 0.0,
-            orbital_speed: 0.0,
-            scale: 150.0,
-            rotation: Vec3::new(0.0, 0.0, 0.0),
-            shader_type: ShaderType::Sun,
-            rotation_speed: 0.005,
-            name: "Sol",
-            color: 0xFFFF00,
-        },
-        CelestialBody {
-            orbital_radius: 300.0,
-            orbital_angle: 0.0,
-            orbital_speed: 0.015,
-            scale: 60.0,
-            rotation: Vec3::new(0.0, 0.0, 0.0),
-            shader_type: ShaderType::RockyPlanet,
-            rotation_speed: 0.02,
-            name: "Planeta Rocoso",
-            color: 0x4488FF,
-        },
-        CelestialBody {
-            orbital_radius: 500.0,
-            orbital_angle: PI / 2.0,
-            orbital_speed: 0.012,
-            scale: 70.0,
-            rotation: Vec3::new(0.0, 0.0, 0.0),
-            shader_type: ShaderType::VolcanicPlanet,
-            rotation_speed: 0.018,
-            name: "Planeta Volcánico",
-            color: 0xFF4400,
-        },
-        CelestialBody {
-            orbital_radius: 750.0,
-            orbital_angle: PI,
-            orbital_speed: 0.008,
-            scale: 120.0,
-            rotation: Vec3::new(0.0, 0.0, 0.0),
-            shader_type: ShaderType::GasGiant,
-            rotation_speed: 0.015,
-            name: "Gigante Gaseoso",
-            color: 0xFFAA66,
-        },
-        CelestialBody {
-            orbital_radius: 1000.0,
-            orbital_angle: PI * 1.5,
-            orbital_speed: 0.005,
-            scale: 80.0,
-            rotation: Vec3::new(0.0, 0.0, 0.0),
-            shader_type: ShaderType::IcePlanet,
-            rotation_speed: 0.01,
-            name: "Planeta Helado",
-            color: 0x88DDFF,
-        },
-        CelestialBody {
-            orbital_radius: 400.0,
-            orbital_angle: PI / 4.0,
-            orbital_speed: 0.025,
-            scale: 40.0,
-            rotation: Vec3::new(0.0, 0.0, 0.0),
-            shader_type: ShaderType::Moon,
-            rotation_speed: 0.03,
-            name: "Luna",
-            color: 0xCCCCCC,
-        },
-    ];
-
-    // Luna orbitando el planeta rocoso
-    let mut moon_angle = 0.0f32;
-    let moon_orbital_radius = 100.0;
-    let moon_speed = 0.05;
-
     let mut paused = false;
     let mut show_orbits = true;
     let mut show_ui = true;
+    let mut planet_cull_mode = CullMode::Back;
+    let mut headlight = false;
+    let mut hdr = system.hdr;
+    let mut eye_adaptation = EyeAdaptation::default();
+    let mut bloom_settings = system.bloom;
+    let mut bloom = Bloom::default();
+    let mut post_process = PostProcess::new(system.post_process.clone());
 
     println!("╔════════════════════════════════════════════════════════════╗");
     println!("║          SISTEMA SOLAR 3D - PROYECTO FINAL                ║");
     println!("╚════════════════════════════════════════════════════════════╝");
     println!();
     println!("🌍 CUERPOS CELESTES:");
-    println!("  0: Sol (centro)");
-    println!("  1: Planeta Rocoso");
-    println!("  2: Planeta Volcánico");
-    println!("  3: Gigante Gaseoso");
-    println!("  4: Planeta Helado");
-    println!("  5: Luna");
+    for (index, body) in system.bodies.iter().enumerate() {
+        println!("  {}: {}", index, body.name);
+    }
     println!();
     println!("🎮 CONTROLES:");
     println!("  W/A/S/D     : Mover cámara");
@@ -451,15 +444,25 @@ fn main() {
     println!("  Q/E         : Rotar cámara (yaw)");
     println!("  Z/C         : Rotar cámara (pitch)");
     println!("  0           : Vista general");
-    println!("  1           : Sol");
-    println!("  2           : Planeta Rocoso");
-    println!("  3           : Planeta Volcánico");
-    println!("  4           : Gigante Gaseoso");
-    println!("  5           : Planeta Helado");
-    println!("  6           : Luna");
+    for (index, body) in system.bodies.iter().enumerate().take(WARP_KEYS.len()) {
+        println!("  {}           : {}", index + 1, body.name);
+    }
     println!("  O           : Toggle órbitas");
     println!("  P           : Pausar/Reanudar");
     println!("  H           : Toggle UI");
+    println!("  B           : Cambiar culling (none/back/front)");
+    println!("  G           : Gravedad N cuerpos / órbitas keplerianas");
+    println!("  T           : Tone mapping (Linear/Reinhard/Aces)");
+    println!("  X           : Exposición automática");
+    println!("  -/=         : Bajar/Subir exposición");
+    println!("  M           : Antialiasing (Off/OrderedGrid/RotatedGrid/Fxaa)");
+    println!("  F           : Toggle bloom");
+    println!("  [/]         : Bajar/Subir intensidad del bloom");
+    println!("  ;/'         : Bajar/Subir umbral del bloom");
+    println!("  ,/.         : Achicar/Agrandar radio del bloom");
+    for (index, effect) in post_process.effects.iter().enumerate().take(POST_PROCESS_KEYS.len()) {
+        println!("  F{}          : {}", index + 1, effect.name());
+    }
     println!("  ESC         : Salir");
     println!();
 
@@ -506,64 +509,22 @@ fn main() {
             camera.rotate(0.0, -30.0 * delta_time);
         }
 
-        // Warping a planetas (teclas 1-6)
+        // Warping a cuerpos celestes (teclas 1-9, en el orden de la escena)
         if window.is_key_pressed(Key::Key0, minifb::KeyRepeat::No) {
             camera.start_warp(Vec3::new(0.0, 300.0, 800.0));
             println!("🚀 Warping a: Vista general del sistema");
         }
 
-        // Sol
-        if window.is_key_pressed(Key::Key1, minifb::KeyRepeat::No) {
-            let target = Vec3::new(0.0, 200.0, 400.0);
-            camera.start_warp(target);
-            println!("🚀 Warping a: {}", bodies[0].name);
-        }
-
-        // Planeta Rocoso
-        if window.is_key_pressed(Key::Key2, minifb::KeyRepeat::No) && bodies.len() > 1 {
-            let planet_pos = calculate_planet_position(&bodies[1], sun_center);
-            let target = Vec3::new(planet_pos.x + 200.0, planet_pos.y + 100.0, planet_pos.z + 200.0);
-            camera.start_warp(target);
-            println!("🚀 Warping a: {} en ({:.0}, {:.0}, {:.0})", bodies[1].name, planet_pos.x, planet_pos.y, planet_pos.z);
-        }
-
-        // Planeta Volcánico
-        if window.is_key_pressed(Key::Key3, minifb::KeyRepeat::No) && bodies.len() > 2 {
-            let planet_pos = calculate_planet_position(&bodies[2], sun_center);
-            let target = Vec3::new(planet_pos.x + 220.0, planet_pos.y + 100.0, planet_pos.z + 220.0);
-            camera.start_warp(target);
-            println!("🚀 Warping a: {} en ({:.0}, {:.0}, {:.0})", bodies[2].name, planet_pos.x, planet_pos.y, planet_pos.z);
-        }
-
-        // Gigante Gaseoso
-        if window.is_key_pressed(Key::Key4, minifb::KeyRepeat::No) && bodies.len() > 3 {
-            let planet_pos = calculate_planet_position(&bodies[3], sun_center);
-            let target = Vec3::new(planet_pos.x + 350.0, planet_pos.y + 150.0, planet_pos.z + 350.0);
-            camera.start_warp(target);
-            println!("🚀 Warping a: {} en ({:.0}, {:.0}, {:.0})", bodies[3].name, planet_pos.x, planet_pos.y, planet_pos.z);
-        }
-
-        // Planeta Helado
-        if window.is_key_pressed(Key::Key5, minifb::KeyRepeat::No) && bodies.len() > 4 {
-            let planet_pos = calculate_planet_position(&bodies[4], sun_center);
-            let target = Vec3::new(planet_pos.x + 250.0, planet_pos.y + 120.0, planet_pos.z + 250.0);
-            camera.start_warp(target);
-            println!("🚀 Warping a: {} en ({:.0}, {:.0}, {:.0})", bodies[4].name, planet_pos.x, planet_pos.y, planet_pos.z);
-        }
-
-        // Luna
-        if window.is_key_pressed(Key::Key6, minifb::KeyRepeat::No) && bodies.len() > 1 {
-            let rocky_pos = calculate_planet_position(&bodies[1], sun_center);
-            let moon_pos = Vec3::new(
-                rocky_pos.x + moon_orbital_radius * moon_angle.cos(),
-                rocky_pos.y,
-                rocky_pos.z + moon_orbital_radius * moon_angle.sin(),
-            );
-            let target = Vec3::new(moon_pos.x + 120.0, moon_pos.y + 50.0, moon_pos.z + 120.0);
-            camera.start_warp(target);
-            println!("🚀 Warping a: Luna en ({:.0}, {:.0}, {:.0})", moon_pos.x, moon_pos.y, moon_pos.z);
+        for (index, key) in WARP_KEYS.iter().enumerate().take(system.bodies.len()) {
+            if window.is_key_pressed(*key, minifb::KeyRepeat::No) {
+                let body = &system.bodies[index];
+                let planet_pos = system.body_position(index);
+                let distance = body.warp_distance;
+                let target = Vec3::new(planet_pos.x + distance, planet_pos.y + distance * 0.5, planet_pos.z + distance);
+                camera.start_warp(target);
+                println!("🚀 Warping a: {} en ({:.0}, {:.0}, {:.0})", body.name, planet_pos.x, planet_pos.y, planet_pos.z);
+            }
         }
-        
 
         // Toggles
         if window.is_key_pressed(Key::O, minifb::KeyRepeat::No) {
@@ -578,115 +539,114 @@ fn main() {
             show_ui = !show_ui;
             println!("UI: {}", if show_ui { "✓ Visible" } else { "✗ Oculto" });
         }
+        if window.is_key_pressed(Key::B, minifb::KeyRepeat::No) {
+            planet_cull_mode = planet_cull_mode.next();
+            println!("Culling: {:?}", planet_cull_mode);
+        }
+        if window.is_key_pressed(Key::L, minifb::KeyRepeat::No) {
+            headlight = !headlight;
+            println!("Foco de la nave: {}", if headlight { "✓ Encendido" } else { "✗ Apagado" });
+        }
+        if window.is_key_pressed(Key::G, minifb::KeyRepeat::No) {
+            let mode = match system.mode() {
+                SimulationMode::Kepler => SimulationMode::NBody,
+                SimulationMode::NBody => SimulationMode::Kepler,
+            };
+            system.set_mode(mode);
+            camera.velocity = Vec3::zeros();
+            println!("Simulación: {:?}", mode);
+        }
+        if window.is_key_pressed(Key::T, minifb::KeyRepeat::No) {
+            hdr.tone_mapping = hdr.tone_mapping.next();
+            println!("Tone mapping: {:?}", hdr.tone_mapping);
+        }
+        if window.is_key_pressed(Key::X, minifb::KeyRepeat::No) {
+            hdr.auto_exposure = !hdr.auto_exposure;
+            println!("Exposición automática: {}", if hdr.auto_exposure { "✓ Activada" } else { "✗ Desactivada" });
+        }
+        // Medio paso de exposición por pulsación
+        if window.is_key_pressed(Key::Minus, minifb::KeyRepeat::Yes) {
+            hdr.exposure /= std::f32::consts::SQRT_2;
+            println!("Exposición: {:.2}", hdr.exposure);
+        }
+        if window.is_key_pressed(Key::Equal, minifb::KeyRepeat::Yes) {
+            hdr.exposure *= std::f32::consts::SQRT_2;
+            println!("Exposición: {:.2}", hdr.exposure);
+        }
+        if window.is_key_pressed(Key::M, minifb::KeyRepeat::No) {
+            framebuffer.set_anti_aliasing(framebuffer.anti_aliasing().next());
+            anti_aliasing_timings.reset();
+            println!("Antialiasing: {}", framebuffer.anti_aliasing().label());
+        }
+        if window.is_key_pressed(Key::F, minifb::KeyRepeat::No) {
+            bloom_settings.enabled = !bloom_settings.enabled;
+            println!("Bloom: {}", if bloom_settings.enabled { "✓ Activado" } else { "✗ Desactivado" });
+        }
+        if window.is_key_pressed(Key::LeftBracket, minifb::KeyRepeat::Yes) {
+            bloom_settings.intensity = (bloom_settings.intensity - 0.1).max(0.0);
+            println!("Intensidad del bloom: {:.1}", bloom_settings.intensity);
+        }
+        if window.is_key_pressed(Key::RightBracket, minifb::KeyRepeat::Yes) {
+            bloom_settings.intensity += 0.1;
+            println!("Intensidad del bloom: {:.1}", bloom_settings.intensity);
+        }
+        if window.is_key_pressed(Key::Semicolon, minifb::KeyRepeat::Yes) {
+            bloom_settings.threshold = (bloom_settings.threshold - 0.1).max(0.0);
+            println!("Umbral del bloom: {:.1}", bloom_settings.threshold);
+        }
+        if window.is_key_pressed(Key::Apostrophe, minifb::KeyRepeat::Yes) {
+            bloom_settings.threshold += 0.1;
+            println!("Umbral del bloom: {:.1}", bloom_settings.threshold);
+        }
+        if window.is_key_pressed(Key::Comma, minifb::KeyRepeat::No) {
+            bloom_settings.radius = (bloom_settings.radius - 1).max(1);
+            println!("Radio del bloom: {}", bloom_settings.radius);
+        }
+        if window.is_key_pressed(Key::Period, minifb::KeyRepeat::No) {
+            bloom_settings.radius = (bloom_settings.radius + 1).min(MAX_RADIUS);
+            println!("Radio del bloom: {}", bloom_settings.radius);
+        }
+
+        for (index, key) in POST_PROCESS_KEYS.iter().enumerate() {
+            if window.is_key_pressed(*key, minifb::KeyRepeat::No)
+                && let Some(effect) = post_process.toggle(index)
+            {
+                println!("{}: {}", effect.name(), if effect.enabled() { "✓ Activado" } else { "✗ Desactivado" });
+            }
+        }
 
         // Actualizar warp
         camera.update_warp(delta_time);
 
-        // Clear
-        framebuffer.clear();
-
-        // Render skybox
-        skybox.render_with_twinkle(&mut framebuffer, time);
-
         // Update time
         if !paused {
             time += delta_time;
-            for body in &mut bodies {
-                body.orbital_angle += body.orbital_speed * delta_time;
-                body.rotation.y += body.rotation_speed * delta_time;
-            }
-        }
-
-        // Render planetas
-        for body in &bodies {
-            let world_pos = calculate_planet_position(body, sun_center);
-            let screen_pos = camera.get_screen_position(world_pos, window_width as f32, window_height as f32);
-
-            // Solo renderizar si está delante de la cámara
-            if screen_pos.z > 0.0 {
-                let model_matrix = create_model_matrix(screen_pos, body.scale, body.rotation);
-                let uniforms = Uniforms { model_matrix };
-                render(&mut framebuffer, &uniforms, &sphere_vertices, body.shader_type, time);
-            }
-
-            // Detección de colisiones
-            if camera.check_collision(world_pos, body.scale) {
-                camera.resolve_collision(world_pos, body.scale);
-            }
-        }
-
-        // Render luna orbitando el planeta rocoso
-        if bodies.len() > 1 {
-            let rocky_planet = &bodies[1];
-            let rocky_pos = calculate_planet_position(rocky_planet, sun_center);
-            
-            // Actualizar ángulo de la luna
-            if !paused {
-                moon_angle += moon_speed * delta_time;
-            }
-            
-            // Calcular posición de la luna
-            let moon_world_pos = Vec3::new(
-                rocky_pos.x + moon_orbital_radius * moon_angle.cos(),
-                rocky_pos.y,
-                rocky_pos.z + moon_orbital_radius * moon_angle.sin(),
-            );
-            
-            let moon_screen = camera.get_screen_position(moon_world_pos, window_width as f32, window_height as f32);
-            
-            if moon_screen.z > 0.0 {
-                let moon_matrix = create_model_matrix(
-                    moon_screen,
-                    25.0,  // Tamaño de la luna
-                    Vec3::new(0.0, moon_angle * 2.0, 0.0),
-                );
-                let moon_uniforms = Uniforms { model_matrix: moon_matrix };
-                render(&mut framebuffer, &moon_uniforms, &sphere_vertices, ShaderType::Moon, time);
-            }
-            
-            // Órbita de la luna (opcional)
-            if show_orbits {
-                draw_orbit(
-                    &mut framebuffer,
-                    rocky_pos,
-                    moon_orbital_radius,
-                    0xCCCCCC,
-                    &camera,
-                    window_width as f32,
-                    window_height as f32,
-                );
-            }
+            system.update(delta_time);
+            camera.apply_gravity(system.gravity_at(camera.position), delta_time);
         }
 
-        // Render órbitas
-        if show_orbits {
-            for body in &bodies {
-                if body.orbital_radius > 0.0 {
-                    draw_orbit(
-                        &mut framebuffer,
-                        sun_center,
-                        body.orbital_radius,
-                        body.color,
-                        &camera,
-                        window_width as f32,
-                        window_height as f32,
-                    );
-                }
-            }
-        }
+        // Detección de colisiones
+        system.resolve_collisions(&mut camera);
 
+        let settings = RenderSettings { show_orbits, planet_cull_mode, headlight };
+        let render_start = Instant::now();
+        render_scene(&mut framebuffer, &system, &camera, &assets, &settings, time);
+        let render_time = render_start.elapsed();
+        let anti_aliasing_start = Instant::now();
+        framebuffer.resolve_samples();
+        let mut anti_aliasing_time = anti_aliasing_start.elapsed();
 
-        // Render nave espacial siguiendo la cámara
-        let spaceship_offset = camera.target * 100.0 + Vec3::new(30.0, -20.0, 0.0);
-        let spaceship_pos_world = camera.position + spaceship_offset;
-        let spaceship_screen = camera.get_screen_position(spaceship_pos_world, window_width as f32, window_height as f32);
-
-        if spaceship_screen.z > 0.0 {
-            let spaceship_rotation = Vec3::new(0.0, camera.yaw.to_radians() + PI / 2.0, 0.0);
-            let spaceship_matrix = create_model_matrix(spaceship_screen, 15.0, spaceship_rotation);
-            let spaceship_uniforms = Uniforms { model_matrix: spaceship_matrix };
-            render(&mut framebuffer, &spaceship_uniforms, &spaceship_vertices, ShaderType::Spaceship, time);
+        bloom.apply(&bloom_settings, &mut framebuffer);
+        let exposure = eye_adaptation.update(&hdr, &framebuffer, delta_time);
+        framebuffer.resolve(hdr.tone_mapping, exposure);
+        if framebuffer.anti_aliasing() == AntiAliasing::Fxaa {
+            let fxaa_start = Instant::now();
+            fxaa.apply(&mut framebuffer);
+            anti_aliasing_time += fxaa_start.elapsed();
         }
+        anti_aliasing_timings.record(render_time, anti_aliasing_time);
+        // La UI se dibuja encima, sin pasar por el post-proceso
+        post_process.apply(&mut framebuffer, time);
 
         // UI simple
         if show_ui && !camera.is_warping {
@@ -701,15 +661,14 @@ fn main() {
                 let progress_percent = (camera.warp_progress * 100.0) as i32;
                 draw_text(&mut framebuffer, 20, 55, &format!("Progress: {}%", progress_percent), 0xFFFF00);
             } else {
-                draw_text(&mut framebuffer, 20, 40, "WASD: Move | Space/Shift: Up/Down", ui_color);
-                draw_text(&mut framebuffer, 20, 55, "QE: Rotate | 0-6: Warp | O: Orbits | P: Pause | H: UI", ui_color);
+                draw_text(&mut framebuffer, 20, 40, "WASD: Move | Space/Shift: Up/Down | QE: Rotate | 0-9: Warp | P: Pause | H: UI", ui_color);
+                draw_text(&mut framebuffer, 20, 55, "O: Orbits | B: Culling | L: Light | G: Gravity | M: AA", ui_color);
+                draw_text(&mut framebuffer, 20, 70, "T/X/-/=: Exposure | F/[]/;'/,.: Bloom | F1-F9: Post FX", ui_color);
             }
             
-            draw_text(&mut framebuffer, 20, 55, "QE: Rotate | 0-6: Warp | O: Orbits | P: Pause | H: UI", ui_color);
-            
             // FPS Counter (opcional)
             let fps = (1.0 / delta_time) as i32;
-            draw_text(&mut framebuffer, 20, 75, &format!("FPS: {}", fps), ui_color);
+            draw_text(&mut framebuffer, 20, 90, &format!("FPS: {}", fps), ui_color);
             
             // Status
             if paused {
@@ -719,6 +678,33 @@ fn main() {
             if show_orbits {
                 draw_text(&mut framebuffer, framebuffer_width - 150, 40, "Orbits: ON", 0x00FF00);
             }
+
+            if system.mode() == SimulationMode::NBody {
+                draw_text(&mut framebuffer, framebuffer_width - 150, 60, "N-Body: ON", 0xFFAA00);
+            }
+
+            if headlight {
+                draw_text(&mut framebuffer, framebuffer_width - 150, 80, "Headlight: ON", 0xFFFFAA);
+            }
+
+            let exposure_mode = if hdr.auto_exposure { "auto" } else { "manual" };
+            draw_text(&mut framebuffer, 20, 110, &format!("{:?} | Exposure: {:.2} ({})", hdr.tone_mapping, exposure, exposure_mode), ui_color);
+            if bloom_settings.enabled {
+                draw_text(&mut framebuffer, 20, 125, &format!("Bloom: threshold {:.1} | intensity {:.1} | radius {}", bloom_settings.threshold, bloom_settings.intensity, bloom_settings.radius), ui_color);
+            } else {
+                draw_text(&mut framebuffer, 20, 125, "Bloom: OFF", ui_color);
+            }
+            if !post_process.effects.is_empty() {
+                let effects: Vec<String> = post_process
+                    .effects
+                    .iter()
+                    .enumerate()
+                    .map(|(index, effect)| format!("F{} {} {}", index + 1, effect.name(), if effect.enabled() { "ON" } else { "OFF" }))
+                    .collect();
+                draw_text(&mut framebuffer, 20, 140, &format!("Post: {}", effects.join(" | ")), ui_color);
+            }
+            let anti_aliasing_summary = anti_aliasing_timings.summary(framebuffer.anti_aliasing());
+            draw_text(&mut framebuffer, 20, 155, &anti_aliasing_summary, ui_color);
         }
 
         // Controles de cámara 3D (SOLO si no está en warp)
@@ -767,10 +753,249 @@ fn main() {
 }
 
 
-fn calculate_planet_position(body: &CelestialBody, center: Vec3) -> Vec3 {
-    Vec3::new(
-        center.x + body.orbital_radius * body.orbital_angle.cos(),
-        center.y,
-        center.z + body.orbital_radius * body.orbital_angle.sin(),
-    )
-}
\ No newline at end of file
+struct SceneAssets {
+    sphere_vertices: Vec<Vertex>,
+    spaceship_vertices: Vec<Vertex>,
+    skybox: Skybox,
+}
+
+fn load_scene_assets(width: usize, height: usize) -> SceneAssets {
+    // Cargar modelos
+    let sphere_obj = Obj::load("../assets/models/sphere.obj").expect("Failed to load sphere.obj");
+    let spaceship_obj = Obj::load("../assets/models/spaceship.obj").expect("Failed to load spaceship.obj");
+
+    SceneAssets {
+        sphere_vertices: sphere_obj.get_vertex_array(),
+        spaceship_vertices: spaceship_obj.get_vertex_array(),
+        // Crear skybox con 1500 estrellas
+        skybox: Skybox::new(1500, width, height),
+    }
+}
+
+// Superficie pendiente de la pasada transparente
+struct TransparentDraw<'a> {
+    uniforms: Uniforms,
+    vertices: &'a [Vertex],
+    shader_type: Option<ShaderType>,
+    state: RenderState,
+    camera_distance: f32,
+}
+
+struct RenderSettings {
+    show_orbits: bool,
+    planet_cull_mode: CullMode,
+    headlight: bool,
+}
+
+// Dibuja un frame completo del sistema (sin UI) en el framebuffer
+fn render_scene(framebuffer: &mut Framebuffer, system: &SolarSystem, camera: &Camera, assets: &SceneAssets, settings: &RenderSettings, time: f32) {
+    let width = framebuffer.width as f32;
+    let height = framebuffer.height as f32;
+
+    // Clear
+    framebuffer.clear();
+
+    // Render skybox
+    assets.skybox.render_with_twinkle(framebuffer, time);
+
+    // Matrices de cámara para este frame
+    let view_matrix = camera.get_view_matrix();
+    let projection_matrix = camera.get_projection_matrix(width / height);
+    let viewport_matrix = create_viewport_matrix(width, height);
+    let camera_right = camera.target.cross(&camera.up).normalize();
+    let camera_up = camera_right.cross(&camera.target).normalize();
+
+    // Luces del frame: las estrellas, las de la escena y el foco de la nave
+    let mut lights = system.lights();
+    if settings.headlight {
+        lights.push(Light {
+            kind: LightKind::Spot {
+                position: camera.position,
+                direction: camera.target,
+                falloff_distance: 600.0,
+                inner_angle: 0.25,
+                outer_angle: 0.4,
+            },
+            color: Vec3::new(1.0, 0.95, 0.85),
+            intensity: 1.5,
+        });
+    }
+    let lights: Arc<[Light]> = lights.into();
+    let occluders: Arc<[Occluder]> = system.occluders(None).into();
+
+    // Pasada opaca de los cuerpos celestes; los transparentes y los anillos se guardan
+    // para dibujarlos al final de atrás hacia delante
+    let mut transparent = Vec::new();
+    for (index, body) in system.bodies.iter().enumerate() {
+        // Los pivotes (sin shader ni textura) solo sirven de centro de órbita
+        if !body.is_visible() {
+            continue;
+        }
+        // Transformación de mundo del grafo, con la rotación y escala propias del cuerpo
+        let local_model = create_model_matrix(Vec3::new(0.0, 0.0, 0.0), body.scale, body.rotation);
+
+        let model_matrix = system.world_transform(index) * local_model;
+
+        let uniforms = Uniforms {
+            model_matrix,
+            view_matrix,
+            projection_matrix,
+            viewport_matrix,
+            normal_matrix: create_normal_matrix(&model_matrix),
+            time,
+            shader_params: body.shader_params,
+            texture: body.texture.clone(),
+            normal_map: body.normal_map.clone(),
+            atmosphere: None,
+            clouds: body.clouds,
+            camera_position: camera.position,
+            lights: lights.clone(),
+            occluders: occluders.clone(),
+            ambient: system.lighting.ambient,
+            emissive: body.emission.is_some(),
+        };
+        let vertices = match body.model {
+            BodyModel::Sphere => &assets.sphere_vertices,
+            BodyModel::Spaceship => &assets.spaceship_vertices,
+        };
+        let camera_distance = (system.body_position(index) - camera.position).magnitude();
+
+        // Las nubes giran a su ritmo: la densidad se evalúa en ejes de mundo, así que la
+        // esfera no lleva la rotación del cuerpo
+        if let Some(clouds) = body.clouds {
+            let shell_scale = clouds.shell_scale(body.scale);
+            let shell_model = system.world_transform(index)
+                * create_model_matrix(Vec3::new(0.0, 0.0, 0.0), shell_scale, Vec3::new(0.0, 0.0, 0.0));
+            let shell_uniforms = Uniforms {
+                model_matrix: shell_model,
+                normal_matrix: create_normal_matrix(&shell_model),
+                shader_params: ShaderParams::default(),
+                texture: None,
+                normal_map: None,
+                emissive: false,
+                ..uniforms.clone()
+            };
+            let cull_mode = if camera_distance > shell_scale { CullMode::Back } else { CullMode::Front };
+            transparent.push(TransparentDraw {
+                uniforms: shell_uniforms,
+                vertices: &assets.sphere_vertices,
+                shader_type: Some(ShaderType::Clouds),
+                state: RenderState { cull_mode, blend_mode: Some(BlendMode::Alpha) },
+                camera_distance,
+            });
+        }
+
+        // La atmósfera suma luz sobre el cuerpo y el fondo. Se dibuja una sola cara de la
+        // esfera por píxel: la de delante desde fuera y la interior desde dentro
+        if let Some(atmosphere) = body.atmosphere {
+            let shell_scale = atmosphere.shell_scale(body.scale);
+            let shell_model = system.world_transform(index)
+                * create_model_matrix(Vec3::new(0.0, 0.0, 0.0), shell_scale, Vec3::new(0.0, 0.0, 0.0));
+            let shell_uniforms = Uniforms {
+                model_matrix: shell_model,
+                normal_matrix: create_normal_matrix(&shell_model),
+                texture: None,
+                normal_map: None,
+                atmosphere: Some(atmosphere),
+                clouds: None,
+                occluders: system.occluders(Some(index)).into(),
+                emissive: false,
+                ..uniforms.clone()
+            };
+            let cull_mode = if camera_distance > shell_scale { CullMode::Back } else { CullMode::Front };
+            transparent.push(TransparentDraw {
+                uniforms: shell_uniforms,
+                vertices: &assets.sphere_vertices,
+                shader_type: Some(ShaderType::Atmosphere),
+                state: RenderState { cull_mode, blend_mode: Some(BlendMode::Additive) },
+                camera_distance,
+            });
+        }
+
+        // El anillo comparte la escala del cuerpo pero no su giro; se ve por las dos caras
+        if let Some(ring) = &body.ring {
+            let ring_model = system.world_transform(index)
+                * create_model_matrix(Vec3::new(0.0, 0.0, 0.0), body.scale, Vec3::new(ring.tilt, 0.0, 0.0));
+            let ring_uniforms = Uniforms {
+                model_matrix: ring_model,
+                normal_matrix: create_normal_matrix(&ring_model),
+                texture: None,
+                normal_map: None,
+                // El anillo queda fuera de la capa de nubes: no recibe su sombra
+                clouds: None,
+                emissive: false,
+                ..uniforms.clone()
+            };
+            transparent.push(TransparentDraw {
+                uniforms: ring_uniforms,
+                vertices: &ring.vertices,
+                shader_type: Some(ShaderType::Ring),
+                state: RenderState::transparent(BlendMode::Alpha),
+                camera_distance,
+            });
+        }
+
+        match body.blend_mode {
+            Some(blend_mode) => transparent.push(TransparentDraw {
+                uniforms,
+                vertices,
+                shader_type: body.shader_type,
+                state: RenderState::transparent(blend_mode),
+                camera_distance,
+            }),
+            None => {
+                let cull_mode = match body.model {
+                    BodyModel::Sphere => settings.planet_cull_mode,
+                    BodyModel::Spaceship => CullMode::None,
+                };
+                render(framebuffer, &uniforms, vertices, body.shader_type, RenderState::opaque(cull_mode));
+            }
+        }
+    }
+
+    // Render nave espacial siguiendo la cámara
+    let spaceship_offset = camera.target * 100.0 + camera_right * 30.0 - camera_up * 20.0;
+    let spaceship_pos_world = camera.position + spaceship_offset;
+
+    // Orienta la nariz de la nave (+Z del modelo) hacia donde mira la cámara
+    let spaceship_rotation = Vec3::new(0.0, PI / 2.0 - camera.yaw.to_radians(), 0.0);
+    let spaceship_model = create_model_matrix(spaceship_pos_world, 2.0, spaceship_rotation);
+    let spaceship_uniforms = Uniforms {
+        model_matrix: spaceship_model,
+        view_matrix,
+        projection_matrix,
+        viewport_matrix,
+        normal_matrix: create_normal_matrix(&spaceship_model),
+        time,
+        shader_params: ShaderParams::default(),
+        texture: None,
+        normal_map: None,
+        atmosphere: None,
+        clouds: None,
+        camera_position: camera.position,
+        lights,
+        occluders,
+        ambient: system.lighting.ambient,
+        emissive: false,
+    };
+    // Sin culling: el winding de la nave no es confiable
+    render(framebuffer, &spaceship_uniforms, &assets.spaceship_vertices, Some(ShaderType::Spaceship), RenderState::opaque(CullMode::None));
+
+    // Pasada transparente: prueba la profundidad de lo opaco sin escribirla
+    transparent.sort_by(|a, b| b.camera_distance.total_cmp(&a.camera_distance));
+    for draw in &transparent {
+        render(framebuffer, &draw.uniforms, draw.vertices, draw.shader_type, draw.state);
+    }
+
+    // Render órbitas al final: no escriben profundidad, así que lo opaco dibujado después
+    // (la nave) las pisaría aunque quedara detrás
+    if settings.show_orbits {
+        let view_projection = projection_matrix * view_matrix;
+        for (index, body) in system.bodies.iter().enumerate() {
+            if body.orbit.semi_major_axis > 0.0 {
+                let style = system.orbits.line_style(body.color);
+                draw_orbit(framebuffer, system.orbit_center(index), &body.orbit, style, &view_projection, &viewport_matrix);
+            }
+        }
+    }
+}
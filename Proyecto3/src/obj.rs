@@ -1,4 +1,5 @@
 use tobj;
+use std::f32::consts::PI;
 use nalgebra_glm::{Vec2, Vec3};
 use crate::vertex::Vertex;
 
@@ -25,10 +26,10 @@ impl Obj {
             let mesh = model.mesh;
             Mesh {
                 vertices: mesh.positions.chunks(3)
-                    .map(|v| Vec3::new(v[0], -v[1], -v[2]))
+                    .map(|v| Vec3::new(v[0], v[1], v[2]))
                     .collect(),
                 normals: mesh.normals.chunks(3)
-                    .map(|n| Vec3::new(n[0], -n[1], -n[2]))
+                    .map(|n| Vec3::new(n[0], n[1], n[2]))
                     .collect(),
                 texcoords: mesh.texcoords.chunks(2)
                     .map(|t| Vec2::new(t[0], 1.0 - t[1]))
@@ -44,6 +45,7 @@ impl Obj {
         let mut vertices = Vec::new();
 
         for mesh in &self.meshes {
+            let tangents = mesh.tangents();
             for &index in &mesh.indices {
                 let position = mesh.vertices[index as usize];
                 let normal = mesh.normals.get(index as usize)
@@ -53,10 +55,81 @@ impl Obj {
                     .cloned()
                     .unwrap_or(Vec2::new(0.0, 0.0));
 
-                vertices.push(Vertex::new(position, normal, tex_coords));
+                let mut vertex = Vertex::new(position, normal, tex_coords);
+                vertex.tangent = tangents.get(index as usize).cloned().unwrap_or(Vec3::new(0.0, 0.0, 0.0));
+                vertices.push(vertex);
+            }
+
+            // Modelos sin UV (la esfera): proyección equirectangular desde el centro
+            if mesh.texcoords.is_empty() {
+                let start = vertices.len() - mesh.indices.len();
+                for triangle in vertices[start..].chunks_mut(3) {
+                    spherical_tex_coords(triangle);
+                }
             }
         }
 
         vertices
     }
+}
+
+impl Mesh {
+    // Tangente por vértice (dirección de +u) promediando la de cada triángulo que lo usa
+    fn tangents(&self) -> Vec<Vec3> {
+        let mut tangents = vec![Vec3::new(0.0, 0.0, 0.0); self.vertices.len()];
+        if self.texcoords.is_empty() {
+            return tangents;
+        }
+
+        for triangle in self.indices.chunks(3) {
+            let [i0, i1, i2] = [triangle[0] as usize, triangle[1] as usize, triangle[2] as usize];
+            let edge1 = self.vertices[i1] - self.vertices[i0];
+            let edge2 = self.vertices[i2] - self.vertices[i0];
+            let duv1 = self.texcoords[i1] - self.texcoords[i0];
+            let duv2 = self.texcoords[i2] - self.texcoords[i0];
+
+            let determinant = duv1.x * duv2.y - duv2.x * duv1.y;
+            if determinant.abs() < 1e-8 {
+                continue;
+            }
+            let tangent = (edge1 * duv2.y - edge2 * duv1.y) / determinant;
+            for index in [i0, i1, i2] {
+                tangents[index] += tangent;
+            }
+        }
+
+        tangents
+    }
+}
+
+// UV esféricas para un triángulo: u = longitud, v = colatitud (0 en el polo norte).
+// Corrige el salto de u en la costura y usa la u de los otros vértices en los polos.
+fn spherical_tex_coords(triangle: &mut [Vertex]) {
+    let mut poles = [false; 3];
+    for (vertex, pole) in triangle.iter_mut().zip(poles.iter_mut()) {
+        let p = vertex.position.normalize();
+        let u = 0.5 + p.z.atan2(p.x) / (2.0 * PI);
+        let v = p.y.clamp(-1.0, 1.0).acos() / PI;
+        vertex.tex_coords = Vec2::new(u, v);
+        // Dirección en la que crece u (hacia el este); nula en los polos
+        vertex.tangent = Vec3::new(-p.z, 0.0, p.x);
+        *pole = p.x.abs() < 1e-4 && p.z.abs() < 1e-4;
+    }
+
+    let max_u = triangle.iter().map(|v| v.tex_coords.x).fold(f32::MIN, f32::max);
+    for (vertex, &pole) in triangle.iter_mut().zip(&poles) {
+        if !pole && max_u - vertex.tex_coords.x > 0.5 {
+            vertex.tex_coords.x += 1.0;
+        }
+    }
+
+    let others: Vec<f32> = triangle.iter().zip(&poles).filter(|&(_, &pole)| !pole).map(|(v, _)| v.tex_coords.x).collect();
+    if !others.is_empty() {
+        let average = others.iter().sum::<f32>() / others.len() as f32;
+        for (vertex, &pole) in triangle.iter_mut().zip(&poles) {
+            if pole {
+                vertex.tex_coords.x = average;
+            }
+        }
+    }
 }
\ No newline at end of file
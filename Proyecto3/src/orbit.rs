@@ -0,0 +1,158 @@
+// orbit.rs - Órbitas keplerianas: elementos orbitales y ecuación de Kepler
+//
+// El plano de referencia (eclíptica) es XZ con Y hacia arriba. Con inclinación, nodo y
+// periapsis en cero la órbita empieza en +X y avanza hacia +Z, igual que las órbitas
+// circulares originales.
+
+use std::f32::consts::PI;
+use nalgebra_glm::Vec3;
+
+const KEPLER_TOLERANCE: f32 = 1e-6;
+const KEPLER_MAX_ITERATIONS: usize = 16;
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct OrbitalElements {
+    pub semi_major_axis: f32,
+    pub eccentricity: f32,
+    pub inclination: f32,
+    pub longitude_of_ascending_node: f32,
+    pub argument_of_periapsis: f32,
+    pub mean_anomaly_at_epoch: f32,
+    // Radianes de anomalía media por segundo
+    pub mean_motion: f32,
+}
+
+impl OrbitalElements {
+    pub fn mean_anomaly_at(&self, time: f32) -> f32 {
+        self.mean_anomaly_at_epoch + self.mean_motion * time
+    }
+
+    // Posición relativa al cuerpo central para una anomalía media dada
+    pub fn position(&self, mean_anomaly: f32) -> Vec3 {
+        let eccentric_anomaly = solve_kepler(mean_anomaly, self.eccentricity);
+        self.position_at_eccentric_anomaly(eccentric_anomaly)
+    }
+
+    // Punto de la elipse para una anomalía excéntrica (también sirve para dibujarla)
+    pub fn position_at_eccentric_anomaly(&self, eccentric_anomaly: f32) -> Vec3 {
+        let a = self.semi_major_axis;
+        let e = self.eccentricity;
+        let b = a * (1.0 - e * e).sqrt();
+
+        // Coordenadas en el plano de la órbita, con el periapsis sobre el eje x
+        let x = a * (eccentric_anomaly.cos() - e);
+        let y = b * eccentric_anomaly.sin();
+
+        self.perifocal_to_reference(x, y)
+    }
+
+    // Velocidad relativa al cuerpo central (derivada de `position` respecto al tiempo)
+    pub fn velocity(&self, mean_anomaly: f32) -> Vec3 {
+        let a = self.semi_major_axis;
+        let e = self.eccentricity;
+        let b = a * (1.0 - e * e).sqrt();
+
+        let eccentric_anomaly = solve_kepler(mean_anomaly, e);
+        let (sin_e, cos_e) = eccentric_anomaly.sin_cos();
+        let eccentric_rate = self.mean_motion / (1.0 - e * cos_e);
+
+        self.perifocal_to_reference(-a * sin_e * eccentric_rate, b * cos_e * eccentric_rate)
+    }
+
+    // Rota del plano de la órbita al de referencia: Rz(Ω) · Rx(i) · Rz(ω)
+    fn perifocal_to_reference(&self, x: f32, y: f32) -> Vec3 {
+        let (sin_w, cos_w) = self.argument_of_periapsis.sin_cos();
+        let (sin_o, cos_o) = self.longitude_of_ascending_node.sin_cos();
+        let (sin_i, cos_i) = self.inclination.sin_cos();
+
+        let xw = x * cos_w - y * sin_w;
+        let yw = x * sin_w + y * cos_w;
+
+        let ecliptic_x = xw * cos_o - yw * cos_i * sin_o;
+        let ecliptic_y = xw * sin_o + yw * cos_i * cos_o;
+        let ecliptic_z = yw * sin_i;
+
+        // La eclíptica es XZ y el polo norte es +Y
+        Vec3::new(ecliptic_x, ecliptic_z, ecliptic_y)
+    }
+}
+
+// Resuelve M = E - e·sin(E) con Newton-Raphson
+pub fn solve_kepler(mean_anomaly: f32, eccentricity: f32) -> f32 {
+    let m = (mean_anomaly + PI).rem_euclid(2.0 * PI) - PI;
+
+    // Para órbitas muy excéntricas E = π converge mejor que E = M
+    let mut e_anomaly = if eccentricity < 0.8 { m } else { PI.copysign(m) };
+    for _ in 0..KEPLER_MAX_ITERATIONS {
+        let f = e_anomaly - eccentricity * e_anomaly.sin() - m;
+        let derivative = 1.0 - eccentricity * e_anomaly.cos();
+        let step = f / derivative;
+        e_anomaly -= step;
+        if step.abs() < KEPLER_TOLERANCE {
+            break;
+        }
+    }
+    e_anomaly
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inclined_orbit(eccentricity: f32) -> OrbitalElements {
+        OrbitalElements {
+            semi_major_axis: 10.0,
+            eccentricity,
+            inclination: 0.4,
+            longitude_of_ascending_node: 1.1,
+            argument_of_periapsis: 2.3,
+            mean_anomaly_at_epoch: 0.0,
+            mean_motion: 0.5,
+        }
+    }
+
+    // Anomalías medias con los casos difíciles: cerca de 0, de ±π y fuera de [-π, π]
+    fn mean_anomalies() -> Vec<f32> {
+        let mut anomalies: Vec<f32> = (-20..=20).map(|i| i as f32 * PI / 20.0).collect();
+        anomalies.extend([1e-4, -1e-4, PI - 1e-4, -PI + 1e-4, 7.0, -13.0]);
+        anomalies
+    }
+
+    #[test]
+    fn solve_kepler_satisfies_the_equation() {
+        for eccentricity in [0.0, 0.5, 0.8, 0.97] {
+            for mean_anomaly in mean_anomalies() {
+                let e_anomaly = solve_kepler(mean_anomaly, eccentricity);
+                let residual = e_anomaly - eccentricity * e_anomaly.sin() - mean_anomaly;
+                // M se compara módulo 2π
+                let residual = (residual + PI).rem_euclid(2.0 * PI) - PI;
+                assert!(residual.abs() < 1e-5, "e = {}, M = {}: residuo {}", eccentricity, mean_anomaly, residual);
+            }
+        }
+    }
+
+    #[test]
+    fn velocity_is_derivative_of_position() {
+        let h = 1e-2;
+        for eccentricity in [0.0, 0.5, 0.8] {
+            let orbit = inclined_orbit(eccentricity);
+            for time in [0.3, 2.0, 5.5, 9.0] {
+                let ahead = orbit.position(orbit.mean_anomaly_at(time + h));
+                let behind = orbit.position(orbit.mean_anomaly_at(time - h));
+                let numeric = (ahead - behind) / (2.0 * h);
+                let velocity = orbit.velocity(orbit.mean_anomaly_at(time));
+                let error = (numeric - velocity).magnitude() / velocity.magnitude();
+                assert!(error < 1e-2, "e = {}, t = {}: {:?} vs {:?}", eccentricity, time, numeric, velocity);
+            }
+        }
+    }
+
+    #[test]
+    fn zero_inclination_stays_in_reference_plane() {
+        let orbit = OrbitalElements { inclination: 0.0, ..inclined_orbit(0.5) };
+        for mean_anomaly in mean_anomalies() {
+            assert!(orbit.position(mean_anomaly).y.abs() < 1e-6);
+            assert!(orbit.velocity(mean_anomaly).y.abs() < 1e-6);
+        }
+    }
+}
@@ -0,0 +1,219 @@
+// physics.rs - Simulación gravitacional de N cuerpos con Verlet de velocidad
+//
+// El integrador avanza con un paso fijo independiente del frame rate: el tiempo de cada
+// frame se acumula y se consumen tantos pasos como quepan, hasta MAX_STEPS_PER_ADVANCE.
+
+use nalgebra_glm::Vec3;
+use serde::Deserialize;
+
+// Tras un frame muy largo (ventana arrastrada, breakpoint) el tiempo que no cabe en estos
+// pasos se descarta: ponerse al día haría cada frame más lento que el anterior
+const MAX_STEPS_PER_ADVANCE: usize = 250;
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PhysicsSettings {
+    // Empezar la escena en modo N cuerpos en vez de órbitas keplerianas
+    pub enabled: bool,
+    pub gravitational_constant: f32,
+    // Paso fijo del integrador en segundos
+    pub timestep: f32,
+    // Evita aceleraciones infinitas cuando dos cuerpos casi coinciden
+    pub softening: f32,
+}
+
+impl Default for PhysicsSettings {
+    fn default() -> Self {
+        PhysicsSettings {
+            enabled: false,
+            gravitational_constant: 1.0,
+            timestep: 0.01,
+            softening: 1.0,
+        }
+    }
+}
+
+pub struct NBody {
+    pub positions: Vec<Vec3>,
+    pub velocities: Vec<Vec3>,
+    masses: Vec<f32>,
+    accelerations: Vec<Vec3>,
+    settings: PhysicsSettings,
+    accumulator: f32,
+}
+
+impl NBody {
+    pub fn new(positions: Vec<Vec3>, velocities: Vec<Vec3>, masses: Vec<f32>, settings: PhysicsSettings) -> Self {
+        let mut nbody = NBody {
+            accelerations: vec![Vec3::zeros(); positions.len()],
+            positions,
+            velocities,
+            masses,
+            settings,
+            accumulator: 0.0,
+        };
+        nbody.remove_net_momentum();
+        nbody.compute_accelerations();
+        nbody
+    }
+
+    // Deja el centro de masa en reposo para que el sistema no se desplace
+    fn remove_net_momentum(&mut self) {
+        let total_mass: f32 = self.masses.iter().sum();
+        if total_mass <= 0.0 {
+            return;
+        }
+        let momentum = self
+            .velocities
+            .iter()
+            .zip(&self.masses)
+            .fold(Vec3::zeros(), |sum, (velocity, &mass)| sum + velocity * mass);
+        let drift = momentum / total_mass;
+        for velocity in &mut self.velocities {
+            *velocity -= drift;
+        }
+    }
+
+    pub fn advance(&mut self, delta_time: f32) {
+        self.accumulator += delta_time;
+        let mut steps = 0;
+        while self.accumulator >= self.settings.timestep {
+            if steps == MAX_STEPS_PER_ADVANCE {
+                self.accumulator = 0.0;
+                break;
+            }
+            self.step(self.settings.timestep);
+            self.accumulator -= self.settings.timestep;
+            steps += 1;
+        }
+    }
+
+    fn step(&mut self, dt: f32) {
+        for ((position, velocity), acceleration) in self.positions.iter_mut().zip(&self.velocities).zip(&self.accelerations) {
+            *position += velocity * dt + acceleration * (0.5 * dt * dt);
+        }
+        let previous = self.accelerations.clone();
+        self.compute_accelerations();
+        for ((velocity, old), new) in self.velocities.iter_mut().zip(&previous).zip(&self.accelerations) {
+            *velocity += (old + new) * (0.5 * dt);
+        }
+    }
+
+    fn compute_accelerations(&mut self) {
+        for i in 0..self.positions.len() {
+            self.accelerations[i] = self.gravity_at(self.positions[i], Some(i));
+        }
+    }
+
+    // Aceleración gravitacional en un punto; `exclude` omite al propio cuerpo
+    pub fn gravity_at(&self, point: Vec3, exclude: Option<usize>) -> Vec3 {
+        let softening_sq = self.settings.softening * self.settings.softening;
+        let mut acceleration = Vec3::zeros();
+        for (j, (&position, &mass)) in self.positions.iter().zip(&self.masses).enumerate() {
+            if mass <= 0.0 || exclude == Some(j) {
+                continue;
+            }
+            let offset = position - point;
+            let distance_sq = offset.magnitude_squared() + softening_sq;
+            acceleration += offset * (self.settings.gravitational_constant * mass / (distance_sq * distance_sq.sqrt()));
+        }
+        acceleration
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(timestep: f32, softening: f32) -> PhysicsSettings {
+        PhysicsSettings { timestep, softening, ..PhysicsSettings::default() }
+    }
+
+    // Estrella de masa 100 y planeta de masa 1 en órbita circular a distancia 10
+    fn circular_binary(settings: PhysicsSettings) -> NBody {
+        let (mass, distance) = (100.0, 10.0);
+        let speed = (settings.gravitational_constant * (mass + 1.0) / distance).sqrt();
+        NBody::new(
+            vec![Vec3::zeros(), Vec3::new(distance, 0.0, 0.0)],
+            vec![Vec3::zeros(), Vec3::new(0.0, 0.0, speed)],
+            vec![mass, 1.0],
+            settings,
+        )
+    }
+
+    // Energía cinética más potencial (con el mismo suavizado que la fuerza)
+    fn energy(nbody: &NBody) -> f32 {
+        let softening_sq = nbody.settings.softening * nbody.settings.softening;
+        let mut energy = 0.0;
+        for i in 0..nbody.positions.len() {
+            energy += 0.5 * nbody.masses[i] * nbody.velocities[i].magnitude_squared();
+            for j in i + 1..nbody.positions.len() {
+                let distance = ((nbody.positions[j] - nbody.positions[i]).magnitude_squared() + softening_sq).sqrt();
+                energy -= nbody.settings.gravitational_constant * nbody.masses[i] * nbody.masses[j] / distance;
+            }
+        }
+        energy
+    }
+
+    fn momentum(nbody: &NBody) -> Vec3 {
+        nbody.velocities.iter().zip(&nbody.masses).fold(Vec3::zeros(), |sum, (velocity, &mass)| sum + velocity * mass)
+    }
+
+    #[test]
+    fn circular_orbit_conserves_energy_and_momentum() {
+        let mut nbody = circular_binary(settings(0.01, 0.01));
+        let initial_energy = energy(&nbody);
+        // Periodo 2π·sqrt(d³ / G(M + m)) ≈ 19.8 s: 10 vueltas
+        for _ in 0..200 {
+            nbody.advance(1.0);
+        }
+        let drift = ((energy(&nbody) - initial_energy) / initial_energy).abs();
+        assert!(drift < 1e-3, "la energía derivó un {}", drift);
+        assert!(momentum(&nbody).magnitude() < 1e-3, "momento neto {:?}", momentum(&nbody));
+        let distance = (nbody.positions[1] - nbody.positions[0]).magnitude();
+        assert!((distance - 10.0).abs() < 0.05, "el radio pasó a {}", distance);
+    }
+
+    #[test]
+    fn result_does_not_depend_on_frame_rate() {
+        // Con paso 0.007 los 2 s dejan medio paso en el acumulador, lejos del borde
+        let mut at_30 = circular_binary(settings(0.007, 1.0));
+        let mut at_144 = circular_binary(settings(0.007, 1.0));
+        for _ in 0..60 {
+            at_30.advance(1.0 / 30.0);
+        }
+        for _ in 0..288 {
+            at_144.advance(1.0 / 144.0);
+        }
+        assert_eq!(at_30.positions, at_144.positions);
+        assert_eq!(at_30.velocities, at_144.velocities);
+    }
+
+    #[test]
+    fn long_frame_drops_the_time_past_the_step_cap() {
+        let mut capped = circular_binary(settings(0.01, 1.0));
+        let mut reference = circular_binary(settings(0.01, 1.0));
+        capped.advance(1000.0);
+        for _ in 0..MAX_STEPS_PER_ADVANCE {
+            reference.step(0.01);
+        }
+        assert_eq!(capped.positions, reference.positions);
+        // El resto del frame largo no se arrastra al siguiente
+        capped.advance(0.0);
+        assert_eq!(capped.positions, reference.positions);
+    }
+
+    #[test]
+    fn softening_keeps_overlapping_bodies_finite() {
+        let mut nbody = NBody::new(
+            vec![Vec3::zeros(), Vec3::zeros(), Vec3::new(1e-6, 0.0, 0.0)],
+            vec![Vec3::zeros(); 3],
+            vec![50.0, 50.0, 50.0],
+            settings(0.01, 1.0),
+        );
+        nbody.advance(5.0);
+        let finite = |v: &Vec3| v.iter().all(|c| c.is_finite());
+        assert!(nbody.positions.iter().chain(&nbody.velocities).all(finite));
+        assert!(nbody.gravity_at(Vec3::zeros(), None).iter().all(|c| c.is_finite()));
+    }
+}
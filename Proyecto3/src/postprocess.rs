@@ -0,0 +1,333 @@
+// postprocess.rs - Cadena de post-proceso sobre la imagen final
+//
+// Corre sobre `Framebuffer.buffer` después del tone mapping y antes de la UI, con los
+// efectos en el orden en que aparecen en la escena ([[post_process]]). Todos trabajan con
+// los colores de pantalla (sRGB de 8 bits) y cada uno se puede apagar sin quitarlo de la
+// cadena (teclas F1-F9 en el orden de la escena).
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use nalgebra_glm::Vec3;
+use serde::Deserialize;
+use crate::framebuffer::{pack_pixel, unpack_pixel, Framebuffer};
+use crate::lut::Lut;
+
+// El grano cambia a este ritmo, como en la película
+const GRAIN_FPS: f32 = 24.0;
+// Matriz de Bayer de 4x4 para el dithering ordenado
+const BAYER: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+// Elemento de [[post_process]] en el archivo de escena; `type` elige el efecto
+#[derive(Deserialize)]
+#[serde(tag = "type")]
+pub enum PostEffectDescription {
+    ColorGrading(ColorGradingDescription),
+    Vignette(Vignette),
+    FilmGrain(FilmGrain),
+    ChromaticAberration(ChromaticAberration),
+    Dithering(Dithering),
+}
+
+// La ruta del .cube es relativa a la carpeta del archivo de escena
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ColorGradingDescription {
+    #[serde(default = "default_enabled")]
+    enabled: bool,
+    lut: PathBuf,
+    // Mezcla entre la imagen original (0) y la corregida (1)
+    #[serde(default = "default_strength")]
+    strength: f32,
+}
+
+// Oscurece las esquinas
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Vignette {
+    pub enabled: bool,
+    // Cuánto se oscurecen las esquinas (1 = negro)
+    pub intensity: f32,
+    // Distancia al centro (1 = esquina) donde termina de oscurecerse
+    pub radius: f32,
+    // Ancho de la transición hacia el centro
+    pub softness: f32,
+}
+
+// Ruido de luminancia animado, más visible en los medios tonos
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FilmGrain {
+    pub enabled: bool,
+    pub intensity: f32,
+}
+
+// Separa los canales hacia los bordes como un lente barato: el rojo hacia fuera, el azul hacia dentro
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ChromaticAberration {
+    pub enabled: bool,
+    // Desplazamiento en píxeles en las esquinas
+    pub strength: f32,
+}
+
+// Reduce la profundidad de color con dithering ordenado (Bayer 4x4)
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Dithering {
+    pub enabled: bool,
+    // Bits por canal, de 1 a 8
+    pub bits: u32,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_strength() -> f32 {
+    1.0
+}
+
+impl Default for Vignette {
+    fn default() -> Self {
+        Vignette { enabled: true, intensity: 0.4, radius: 1.0, softness: 0.6 }
+    }
+}
+
+impl Default for FilmGrain {
+    fn default() -> Self {
+        FilmGrain { enabled: true, intensity: 0.05 }
+    }
+}
+
+impl Default for ChromaticAberration {
+    fn default() -> Self {
+        ChromaticAberration { enabled: true, strength: 1.5 }
+    }
+}
+
+impl Default for Dithering {
+    fn default() -> Self {
+        Dithering { enabled: true, bits: 5 }
+    }
+}
+
+#[derive(Clone)]
+pub enum PostEffect {
+    ColorGrading { enabled: bool, lut: Arc<Lut>, strength: f32 },
+    Vignette(Vignette),
+    FilmGrain(FilmGrain),
+    ChromaticAberration(ChromaticAberration),
+    Dithering(Dithering),
+}
+
+impl PostEffect {
+    pub fn load(description: &PostEffectDescription, base_dir: &Path) -> Result<Self, String> {
+        let effect = match description {
+            PostEffectDescription::ColorGrading(grading) => {
+                if !(0.0..=1.0).contains(&grading.strength) {
+                    return Err("post_process: strength de ColorGrading debe estar en [0, 1]".to_string());
+                }
+                PostEffect::ColorGrading {
+                    enabled: grading.enabled,
+                    lut: Arc::new(Lut::load(&base_dir.join(&grading.lut))?),
+                    strength: grading.strength,
+                }
+            }
+            PostEffectDescription::Vignette(vignette) => {
+                if !(0.0..=1.0).contains(&vignette.intensity) || vignette.radius <= 0.0 || vignette.softness < 0.0 {
+                    return Err("post_process: Vignette necesita intensity en [0, 1], radius > 0 y softness >= 0".to_string());
+                }
+                PostEffect::Vignette(*vignette)
+            }
+            PostEffectDescription::FilmGrain(grain) => {
+                if grain.intensity < 0.0 {
+                    return Err("post_process: intensity de FilmGrain no puede ser negativa".to_string());
+                }
+                PostEffect::FilmGrain(*grain)
+            }
+            PostEffectDescription::ChromaticAberration(aberration) => {
+                if aberration.strength < 0.0 {
+                    return Err("post_process: strength de ChromaticAberration no puede ser negativa".to_string());
+                }
+                PostEffect::ChromaticAberration(*aberration)
+            }
+            PostEffectDescription::Dithering(dithering) => {
+                if !(1..=8).contains(&dithering.bits) {
+                    return Err("post_process: bits de Dithering debe estar entre 1 y 8".to_string());
+                }
+                PostEffect::Dithering(*dithering)
+            }
+        };
+        Ok(effect)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PostEffect::ColorGrading { .. } => "ColorGrading",
+            PostEffect::Vignette(_) => "Vignette",
+            PostEffect::FilmGrain(_) => "FilmGrain",
+            PostEffect::ChromaticAberration(_) => "ChromaticAberration",
+            PostEffect::Dithering(_) => "Dithering",
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        match self {
+            PostEffect::ColorGrading { enabled, .. } => *enabled,
+            PostEffect::Vignette(vignette) => vignette.enabled,
+            PostEffect::FilmGrain(grain) => grain.enabled,
+            PostEffect::ChromaticAberration(aberration) => aberration.enabled,
+            PostEffect::Dithering(dithering) => dithering.enabled,
+        }
+    }
+
+    pub fn set_enabled(&mut self, value: bool) {
+        match self {
+            PostEffect::ColorGrading { enabled, .. } => *enabled = value,
+            PostEffect::Vignette(vignette) => vignette.enabled = value,
+            PostEffect::FilmGrain(grain) => grain.enabled = value,
+            PostEffect::ChromaticAberration(aberration) => aberration.enabled = value,
+            PostEffect::Dithering(dithering) => dithering.enabled = value,
+        }
+    }
+}
+
+// Los efectos de la escena, en orden, y una copia de la imagen para los que leen vecinos
+#[derive(Default)]
+pub struct PostProcess {
+    pub effects: Vec<PostEffect>,
+    scratch: Vec<u32>,
+}
+
+impl PostProcess {
+    pub fn new(effects: Vec<PostEffect>) -> Self {
+        PostProcess { effects, scratch: Vec::new() }
+    }
+
+    // Enciende o apaga el efecto `index`; devuelve el efecto si existe
+    pub fn toggle(&mut self, index: usize) -> Option<&PostEffect> {
+        let effect = self.effects.get_mut(index)?;
+        effect.set_enabled(!effect.enabled());
+        Some(effect)
+    }
+
+    // `time` en segundos anima el grano
+    pub fn apply(&mut self, framebuffer: &mut Framebuffer, time: f32) {
+        for effect in self.effects.iter().filter(|effect| effect.enabled()) {
+            match effect {
+                PostEffect::ColorGrading { lut, strength, .. } => color_grading(framebuffer, lut, *strength),
+                PostEffect::Vignette(vignette) => apply_vignette(framebuffer, vignette),
+                PostEffect::FilmGrain(grain) => film_grain(framebuffer, grain, time),
+                PostEffect::ChromaticAberration(aberration) => {
+                    self.scratch.clone_from(&framebuffer.buffer);
+                    chromatic_aberration(framebuffer, &self.scratch, aberration);
+                }
+                PostEffect::Dithering(dithering) => dither(framebuffer, dithering),
+            }
+        }
+    }
+}
+
+// Distancia de (x, y) al centro de la imagen, con 1 en las esquinas
+fn distance_to_center(framebuffer: &Framebuffer, x: usize, y: usize) -> f32 {
+    let half_width = framebuffer.width as f32 * 0.5;
+    let half_height = framebuffer.height as f32 * 0.5;
+    let dx = (x as f32 + 0.5 - half_width) / half_width;
+    let dy = (y as f32 + 0.5 - half_height) / half_height;
+    ((dx * dx + dy * dy) * 0.5).sqrt()
+}
+
+fn color_grading(framebuffer: &mut Framebuffer, lut: &Lut, strength: f32) {
+    for pixel in framebuffer.buffer.iter_mut() {
+        let color = unpack_pixel(*pixel);
+        let graded = lut.sample(color);
+        *pixel = pack_pixel(color + (graded - color) * strength);
+    }
+}
+
+fn apply_vignette(framebuffer: &mut Framebuffer, vignette: &Vignette) {
+    let start = vignette.radius - vignette.softness;
+    for y in 0..framebuffer.height {
+        for x in 0..framebuffer.width {
+            let distance = distance_to_center(framebuffer, x, y);
+            let t = if distance >= vignette.radius {
+                1.0
+            } else if distance <= start {
+                0.0
+            } else {
+                let t = (distance - start) / vignette.softness;
+                t * t * (3.0 - 2.0 * t)
+            };
+            let index = y * framebuffer.width + x;
+            framebuffer.buffer[index] = pack_pixel(unpack_pixel(framebuffer.buffer[index]) * (1.0 - vignette.intensity * t));
+        }
+    }
+}
+
+fn film_grain(framebuffer: &mut Framebuffer, grain: &FilmGrain, time: f32) {
+    let seed = (time * GRAIN_FPS).floor() as u32;
+    for y in 0..framebuffer.height {
+        for x in 0..framebuffer.width {
+            let index = y * framebuffer.width + x;
+            let color = unpack_pixel(framebuffer.buffer[index]);
+            // Las sombras profundas y las luces casi blancas casi no tienen grano
+            let luminance = 0.2126 * color.x + 0.7152 * color.y + 0.0722 * color.z;
+            let weight = 4.0 * luminance * (1.0 - luminance);
+            let noise = hash(x as u32, y as u32, seed) - 0.5;
+            framebuffer.buffer[index] = pack_pixel(color.add_scalar(noise * grain.intensity * weight * 2.0));
+        }
+    }
+}
+
+fn chromatic_aberration(framebuffer: &mut Framebuffer, source: &[u32], aberration: &ChromaticAberration) {
+    let (width, height) = (framebuffer.width, framebuffer.height);
+    let center_x = width as f32 * 0.5;
+    let center_y = height as f32 * 0.5;
+    let corner = (center_x * center_x + center_y * center_y).sqrt();
+    // Canal `shift` (16 = rojo, 0 = azul) muestreado bilinealmente en (x, y)
+    let channel = |x: f32, y: f32, shift: u32| -> u32 {
+        let x = x.clamp(0.0, (width - 1) as f32);
+        let y = y.clamp(0.0, (height - 1) as f32);
+        let (x0, y0) = (x.floor() as usize, y.floor() as usize);
+        let (x1, y1) = ((x0 + 1).min(width - 1), (y0 + 1).min(height - 1));
+        let (fx, fy) = (x - x0 as f32, y - y0 as f32);
+        let at = |x: usize, y: usize| ((source[y * width + x] >> shift) & 0xFF) as f32;
+        let top = at(x0, y0) * (1.0 - fx) + at(x1, y0) * fx;
+        let bottom = at(x0, y1) * (1.0 - fx) + at(x1, y1) * fx;
+        (top * (1.0 - fy) + bottom * fy).round() as u32
+    };
+    for y in 0..height {
+        for x in 0..width {
+            // Dirección desde el centro, con el desplazamiento creciendo hacia las esquinas
+            let dx = (x as f32 + 0.5 - center_x) / corner * aberration.strength;
+            let dy = (y as f32 + 0.5 - center_y) / corner * aberration.strength;
+            let index = y * width + x;
+            let red = channel(x as f32 - dx, y as f32 - dy, 16);
+            let blue = channel(x as f32 + dx, y as f32 + dy, 0);
+            framebuffer.buffer[index] = (red << 16) | (source[index] & 0x00FF00) | blue;
+        }
+    }
+}
+
+fn dither(framebuffer: &mut Framebuffer, dithering: &Dithering) {
+    let levels = ((1u32 << dithering.bits) - 1) as f32;
+    for y in 0..framebuffer.height {
+        for x in 0..framebuffer.width {
+            let threshold = (BAYER[y % 4][x % 4] as f32 + 0.5) / 16.0;
+            let index = y * framebuffer.width + x;
+            let color = unpack_pixel(framebuffer.buffer[index]);
+            framebuffer.buffer[index] = pack_pixel(color.map(|c| (c * levels + threshold - 0.5).round() / levels));
+        }
+    }
+}
+
+// Ruido blanco en [0, 1) a partir de las coordenadas y la semilla del frame
+fn hash(x: u32, y: u32, seed: u32) -> f32 {
+    let mut h = x.wrapping_mul(0x8DA6_B343) ^ y.wrapping_mul(0xD816_3841) ^ seed.wrapping_mul(0xCB1A_B31F);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x2C1B_3C6D);
+    h ^= h >> 12;
+    h = h.wrapping_mul(0x297A_2D39);
+    h ^= h >> 15;
+    (h >> 8) as f32 / (1u32 << 24) as f32
+}
@@ -0,0 +1,74 @@
+// rasterizer.rs - Rasterizador por tiles en paralelo
+
+use std::sync::Mutex;
+use std::thread;
+use crate::framebuffer::{Framebuffer, TILE_SIZE};
+use crate::triangle::{triangle, screen_bounds, RenderState, ShaderType};
+use crate::vertex::Vertex;
+use crate::Uniforms;
+
+// Rectángulo de píxeles inclusivo (min_x, min_y, max_x, max_y)
+pub type PixelRect = (i32, i32, i32, i32);
+
+/// Rasteriza triángulos ya recortados en pantalla, escribiendo directo en el framebuffer.
+/// Los triángulos se agrupan por tile y cada franja de tiles se sombrea en un hilo.
+pub fn rasterize(framebuffer: &mut Framebuffer, triangles: &[[Vertex; 3]], shader_type: Option<ShaderType>, uniforms: &Uniforms, state: RenderState) {
+    if triangles.is_empty() {
+        return;
+    }
+
+    let tiles_x = framebuffer.tiles_x();
+    let tiles_y = framebuffer.tiles_y();
+    let (width, height) = (framebuffer.width, framebuffer.height);
+
+    // Binning: cada tile guarda los índices de los triángulos que lo tocan
+    let mut bins: Vec<Vec<usize>> = vec![Vec::new(); tiles_x * tiles_y];
+    for (index, tri) in triangles.iter().enumerate() {
+        let (min_x, min_y, max_x, max_y) = screen_bounds(&tri[0], &tri[1], &tri[2], width, height);
+        if min_x > max_x || min_y > max_y {
+            continue;
+        }
+        for ty in (min_y as usize / TILE_SIZE)..=(max_y as usize / TILE_SIZE) {
+            for tx in (min_x as usize / TILE_SIZE)..=(max_x as usize / TILE_SIZE) {
+                bins[ty * tiles_x + tx].push(index);
+            }
+        }
+    }
+
+    let rows: Vec<_> = framebuffer
+        .tile_rows()
+        .into_iter()
+        .enumerate()
+        .filter(|(ty, _)| bins[ty * tiles_x..(ty + 1) * tiles_x].iter().any(|bin| !bin.is_empty()))
+        .collect();
+
+    let workers = thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(4)
+        .min(rows.len());
+    let queue = Mutex::new(rows);
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let next = queue.lock().unwrap().pop();
+                let Some((ty, mut row)) = next else {
+                    break;
+                };
+
+                for tx in 0..tiles_x {
+                    let tile: PixelRect = (
+                        (tx * TILE_SIZE) as i32,
+                        row.y0 as i32,
+                        ((tx + 1) * TILE_SIZE).min(width) as i32 - 1,
+                        (row.y0 + row.height) as i32 - 1,
+                    );
+                    for &index in &bins[ty * tiles_x + tx] {
+                        let tri = &triangles[index];
+                        triangle(tri, shader_type, uniforms, state.blend_mode, &mut row, tile);
+                    }
+                }
+            });
+        }
+    });
+}
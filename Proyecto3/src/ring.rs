@@ -0,0 +1,63 @@
+// ring.rs - Anillos planetarios: un anillo plano (annulus) en el ecuador del cuerpo
+//
+// La malla está en espacio del modelo del cuerpo, con los radios en radios del planeta,
+// así que se escala con él. Se sombrea con `ring_shader`, que descarta los huecos.
+
+use std::f32::consts::PI;
+use nalgebra_glm::{Vec2, Vec3};
+use serde::Deserialize;
+use crate::vertex::Vertex;
+
+const RING_SEGMENTS: usize = 128;
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RingDescription {
+    pub inner_radius: f32,
+    pub outer_radius: f32,
+    // Inclinación del plano del anillo respecto al plano XZ del cuerpo (radianes)
+    #[serde(default)]
+    pub tilt: f32,
+}
+
+pub struct Ring {
+    pub tilt: f32,
+    pub vertices: Vec<Vertex>,
+}
+
+impl Ring {
+    pub fn new(description: &RingDescription) -> Result<Self, String> {
+        if !(description.inner_radius > 0.0 && description.inner_radius < description.outer_radius) {
+            return Err("el anillo necesita 0 < inner_radius < outer_radius".to_string());
+        }
+        Ok(Ring {
+            tilt: description.tilt,
+            vertices: annulus(description.inner_radius, description.outer_radius, RING_SEGMENTS),
+        })
+    }
+}
+
+// Triángulos de un anillo en el plano XZ con normal +Y.
+// u recorre el ángulo y v va del borde interior (0) al exterior (1).
+fn annulus(inner_radius: f32, outer_radius: f32, segments: usize) -> Vec<Vertex> {
+    let vertex = |segment: usize, outer: bool| {
+        let u = segment as f32 / segments as f32;
+        let (sin, cos) = (2.0 * PI * u).sin_cos();
+        let radius = if outer { outer_radius } else { inner_radius };
+        let mut vertex = Vertex::new(
+            Vec3::new(cos * radius, 0.0, sin * radius),
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec2::new(u, if outer { 1.0 } else { 0.0 }),
+        );
+        vertex.tangent = Vec3::new(-sin, 0.0, cos);
+        vertex
+    };
+
+    let mut vertices = Vec::with_capacity(segments * 6);
+    for segment in 0..segments {
+        let (inner_a, outer_a) = (vertex(segment, false), vertex(segment, true));
+        let (inner_b, outer_b) = (vertex(segment + 1, false), vertex(segment + 1, true));
+        vertices.extend([inner_a.clone(), outer_a, outer_b.clone(), inner_a, outer_b, inner_b]);
+    }
+    vertices
+}
@@ -0,0 +1,677 @@
+// scene.rs - Grafo de escena de cuerpos celestes cargado desde un archivo (TOML)
+//
+// Cada cuerpo puede orbitar a cualquier otro (`parent`). La transformación de mundo de
+// un cuerpo es la de su padre compuesta con la de su órbita, y se recalcula recorriendo
+// el grafo desde las raíces en cada actualización.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use nalgebra_glm::{Vec3, Vec4, Mat4};
+use serde::Deserialize;
+use crate::atmosphere::{Atmosphere, AtmosphereDescription};
+use crate::camera::Camera;
+use crate::clouds::{CloudLayer, CloudsDescription};
+use crate::color::BlendMode;
+use crate::hdr::HdrSettings;
+use crate::bloom::BloomSettings;
+use crate::line::OrbitSettings;
+use crate::postprocess::{PostEffect, PostEffectDescription};
+use crate::light::{Emission, Light, LightDescription, LightingSettings};
+use crate::shadow::Occluder;
+use crate::orbit::OrbitalElements;
+use crate::physics::{NBody, PhysicsSettings};
+use crate::ring::{Ring, RingDescription};
+use crate::texture::{FilterMode, NormalMap, SurfaceTexture, Texture, TextureMode, WrapMode};
+use crate::triangle::{ShaderParams, ShaderType};
+
+pub const DEFAULT_SCENE: &str = "../assets/scenes/solar_system.toml";
+
+// Malla con la que se dibuja un cuerpo
+#[derive(Clone, Copy, PartialEq, Debug, Default, Deserialize)]
+pub enum BodyModel {
+    #[default]
+    Sphere,
+    Spaceship,
+}
+
+pub struct CelestialBody {
+    pub orbit: OrbitalElements,
+    pub scale: f32,
+    pub rotation: Vec3,
+    // Sin shader ni textura el cuerpo es un pivote invisible (p. ej. el baricentro de estrellas binarias)
+    pub shader_type: Option<ShaderType>,
+    pub shader_params: ShaderParams,
+    pub texture: Option<SurfaceTexture>,
+    pub normal_map: Option<NormalMap>,
+    pub model: BodyModel,
+    pub ring: Option<Ring>,
+    pub atmosphere: Option<Atmosphere>,
+    pub clouds: Option<CloudLayer>,
+    // Con modo de mezcla el cuerpo se dibuja en la pasada transparente
+    pub blend_mode: Option<BlendMode>,
+    // Solo se usa en modo N cuerpos; con masa 0 el cuerpo no atrae a los demás
+    pub mass: f32,
+    // Las estrellas iluminan al resto de cuerpos
+    pub emission: Option<Emission>,
+    pub rotation_speed: f32,
+    pub name: String,
+    pub color: u32, // Para las órbitas
+    pub parent: Option<usize>,
+    pub warp_distance: f32,
+}
+
+impl CelestialBody {
+    pub fn is_visible(&self) -> bool {
+        self.shader_type.is_some() || self.texture.is_some()
+    }
+
+    // Transformación de la órbita relativa al padre
+    pub fn local_transform(&self, time: f32) -> Mat4 {
+        nalgebra_glm::translation(&self.orbit.position(self.orbit.mean_anomaly_at(time)))
+    }
+}
+
+// Formato del archivo de escena
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneFile {
+    #[serde(default)]
+    physics: PhysicsSettings,
+    #[serde(default)]
+    lighting: LightingSettings,
+    #[serde(default)]
+    hdr: HdrSettings,
+    #[serde(default)]
+    bloom: BloomSettings,
+    #[serde(default)]
+    post_process: Vec<PostEffectDescription>,
+    #[serde(default)]
+    orbits: OrbitSettings,
+    #[serde(default)]
+    lights: Vec<LightDescription>,
+    bodies: Vec<BodyDescription>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BodyDescription {
+    name: String,
+    #[serde(default)]
+    parent: Option<String>,
+    #[serde(default)]
+    shader: Option<ShaderType>,
+    #[serde(default)]
+    shader_params: ShaderParams,
+    #[serde(default)]
+    texture: Option<TextureDescription>,
+    #[serde(default)]
+    normal_map: Option<NormalMapDescription>,
+    #[serde(default)]
+    model: BodyModel,
+    #[serde(default)]
+    ring: Option<RingDescription>,
+    #[serde(default)]
+    atmosphere: Option<AtmosphereDescription>,
+    #[serde(default)]
+    clouds: Option<CloudsDescription>,
+    // Sin `blend`, un cuerpo con shader_params.opacity < 1 se mezcla con Alpha
+    #[serde(default)]
+    blend: Option<BlendMode>,
+    // Los nombres de las órbitas circulares originales se aceptan como alias
+    #[serde(default, alias = "orbital_radius")]
+    semi_major_axis: f32,
+    #[serde(default)]
+    eccentricity: f32,
+    #[serde(default)]
+    inclination: f32,
+    #[serde(default)]
+    longitude_of_ascending_node: f32,
+    #[serde(default)]
+    argument_of_periapsis: f32,
+    #[serde(default, alias = "orbital_angle")]
+    mean_anomaly: f32,
+    #[serde(default, alias = "orbital_speed")]
+    mean_motion: f32,
+    #[serde(default)]
+    scale: f32,
+    #[serde(default)]
+    mass: f32,
+    // Los cuerpos con shader Sun emiten luz blanca aunque no lo declaren
+    #[serde(default)]
+    emission: Option<EmissionDescription>,
+    #[serde(default)]
+    rotation_speed: f32,
+    #[serde(default = "default_orbit_color")]
+    color: u32,
+    #[serde(default)]
+    warp_distance: Option<f32>,
+}
+
+// Ruta relativa a la carpeta del archivo de escena
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TextureDescription {
+    path: PathBuf,
+    #[serde(default)]
+    filter: FilterMode,
+    #[serde(default)]
+    wrap: WrapMode,
+    #[serde(default)]
+    mode: TextureMode,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct NormalMapDescription {
+    path: PathBuf,
+    #[serde(default)]
+    filter: FilterMode,
+    #[serde(default)]
+    wrap: WrapMode,
+    // Escala la inclinación de las normales (0 = superficie lisa)
+    #[serde(default = "default_normal_strength")]
+    strength: f32,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EmissionDescription {
+    #[serde(default = "default_emission_color")]
+    color: [f32; 3],
+    #[serde(default = "default_emission_intensity")]
+    intensity: f32,
+    // Por defecto la de [lighting]
+    #[serde(default)]
+    falloff_distance: Option<f32>,
+}
+
+fn default_emission_color() -> [f32; 3] {
+    [1.0, 1.0, 1.0]
+}
+
+fn default_emission_intensity() -> f32 {
+    1.0
+}
+
+fn default_normal_strength() -> f32 {
+    1.0
+}
+
+fn default_orbit_color() -> u32 {
+    0xFFFFFF
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SimulationMode {
+    // Órbitas cinemáticas a partir de los elementos orbitales
+    Kepler,
+    // Gravedad mutua entre los cuerpos con masa
+    NBody,
+}
+
+pub struct SolarSystem {
+    pub sun_center: Vec3,
+    pub bodies: Vec<CelestialBody>,
+    // Tiempo de simulación en segundos desde la época de los elementos orbitales
+    pub time: f32,
+    pub physics: PhysicsSettings,
+    pub lighting: LightingSettings,
+    // Tone mapping y exposición con los que se muestra la escena
+    pub hdr: HdrSettings,
+    // Resplandor de las zonas más brillantes
+    pub bloom: BloomSettings,
+    // Efectos sobre la imagen final, en orden
+    pub post_process: Vec<PostEffect>,
+    // Ancho y opacidad de las líneas de las órbitas
+    pub orbits: OrbitSettings,
+    // Luces declaradas en la escena, además de las estrellas
+    lights: Vec<Light>,
+    children: Vec<Vec<usize>>,
+    roots: Vec<usize>,
+    world_transforms: Vec<Mat4>,
+    nbody: Option<NBody>,
+}
+
+impl SolarSystem {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let source = fs::read_to_string(path)
+            .map_err(|e| format!("no se pudo leer la escena {}: {}", path.display(), e))?;
+        let base_dir = path.parent().unwrap_or(Path::new("."));
+        Self::from_toml(&source, base_dir).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn from_toml(source: &str, base_dir: &Path) -> Result<Self, String> {
+        let file: SceneFile = toml::from_str(source).map_err(|e| e.to_string())?;
+        if file.physics.timestep <= 0.0 {
+            return Err("physics.timestep debe ser positivo".to_string());
+        }
+        if file.lighting.ambient < 0.0 {
+            return Err("lighting.ambient no puede ser negativa".to_string());
+        }
+        if file.lighting.falloff_distance <= 0.0 {
+            return Err("lighting.falloff_distance debe ser positiva".to_string());
+        }
+        file.hdr.validate()?;
+        file.bloom.validate()?;
+        file.orbits.validate()?;
+
+        let mut indices: HashMap<&str, usize> = HashMap::new();
+        for (index, description) in file.bodies.iter().enumerate() {
+            if indices.insert(description.name.as_str(), index).is_some() {
+                return Err(format!("nombre de cuerpo repetido: {}", description.name));
+            }
+            if (description.shader.is_some() || description.texture.is_some()) && description.scale <= 0.0 {
+                return Err(format!("{}: scale debe ser positiva", description.name));
+            }
+            if !(0.0..1.0).contains(&description.eccentricity) {
+                return Err(format!("{}: eccentricity debe estar en [0, 1)", description.name));
+            }
+            if description.semi_major_axis < 0.0 {
+                return Err(format!("{}: semi_major_axis no puede ser negativo", description.name));
+            }
+            if description.mass < 0.0 {
+                return Err(format!("{}: mass no puede ser negativa", description.name));
+            }
+            if !(0.0..=1.0).contains(&description.shader_params.opacity) {
+                return Err(format!("{}: shader_params.opacity debe estar en [0, 1]", description.name));
+            }
+            if let Some(emission) = &description.emission {
+                if emission.intensity < 0.0 || emission.color.iter().any(|&channel| channel < 0.0) {
+                    return Err(format!("{}: el color y la intensidad de emission no pueden ser negativos", description.name));
+                }
+                if emission.falloff_distance.is_some_and(|distance| distance <= 0.0) {
+                    return Err(format!("{}: emission.falloff_distance debe ser positiva", description.name));
+                }
+            }
+        }
+
+        let lights = file
+            .lights
+            .iter()
+            .map(|light| light.to_light(&file.lighting))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Los padres pueden declararse en cualquier orden
+        let mut parents = Vec::with_capacity(file.bodies.len());
+        for description in &file.bodies {
+            let parent = match &description.parent {
+                None => None,
+                Some(parent_name) => Some(*indices.get(parent_name.as_str()).ok_or_else(|| {
+                    format!("{}: el padre {} no existe", description.name, parent_name)
+                })?),
+            };
+            parents.push(parent);
+        }
+
+        // Cada imagen se carga una sola vez aunque la usen varios cuerpos
+        let mut loaded: HashMap<(PathBuf, FilterMode, WrapMode), Arc<Texture>> = HashMap::new();
+        let mut load_shared = |path: &Path, filter: FilterMode, wrap: WrapMode| -> Result<Arc<Texture>, String> {
+            let path = base_dir.join(path);
+            let key = (path.clone(), filter, wrap);
+            if let Some(shared) = loaded.get(&key) {
+                return Ok(shared.clone());
+            }
+            let shared = Arc::new(Texture::load(&path, filter, wrap)?);
+            loaded.insert(key, shared.clone());
+            Ok(shared)
+        };
+        let mut surfaces = Vec::with_capacity(file.bodies.len());
+        for description in &file.bodies {
+            let texture = match &description.texture {
+                None => None,
+                Some(texture) => Some(SurfaceTexture {
+                    texture: load_shared(&texture.path, texture.filter, texture.wrap)?,
+                    mode: texture.mode,
+                }),
+            };
+            let normal_map = match &description.normal_map {
+                None => None,
+                Some(normal_map) => Some(NormalMap {
+                    texture: load_shared(&normal_map.path, normal_map.filter, normal_map.wrap)?,
+                    strength: normal_map.strength,
+                }),
+            };
+            let ring = match &description.ring {
+                None => None,
+                Some(ring) => Some(Ring::new(ring).map_err(|e| format!("{}: {}", description.name, e))?),
+            };
+            let atmosphere = match &description.atmosphere {
+                None => None,
+                Some(atmosphere) => Some(Atmosphere::new(atmosphere).map_err(|e| format!("{}: {}", description.name, e))?),
+            };
+            let clouds = match &description.clouds {
+                None => None,
+                Some(clouds) => Some(CloudLayer::new(clouds).map_err(|e| format!("{}: {}", description.name, e))?),
+            };
+            surfaces.push((texture, normal_map, ring, atmosphere, clouds));
+        }
+
+        let bodies: Vec<CelestialBody> = file
+            .bodies
+            .into_iter()
+            .zip(parents)
+            .zip(surfaces)
+            .map(|((description, parent), (texture, normal_map, ring, atmosphere, clouds))| CelestialBody {
+                orbit: OrbitalElements {
+                    semi_major_axis: description.semi_major_axis,
+                    eccentricity: description.eccentricity,
+                    inclination: description.inclination,
+                    longitude_of_ascending_node: description.longitude_of_ascending_node,
+                    argument_of_periapsis: description.argument_of_periapsis,
+                    mean_anomaly_at_epoch: description.mean_anomaly,
+                    mean_motion: description.mean_motion,
+                },
+                scale: description.scale,
+                rotation: Vec3::new(0.0, 0.0, 0.0),
+                shader_type: description.shader,
+                shader_params: description.shader_params,
+                texture,
+                normal_map,
+                model: description.model,
+                ring,
+                atmosphere,
+                clouds,
+                blend_mode: description.blend.or(
+                    (description.shader_params.opacity < 1.0).then_some(BlendMode::Alpha),
+                ),
+                mass: description.mass,
+                emission: match &description.emission {
+                    Some(emission) => Some(Emission {
+                        color: Vec3::from(emission.color),
+                        intensity: emission.intensity,
+                        falloff_distance: emission.falloff_distance.unwrap_or(file.lighting.falloff_distance),
+                    }),
+                    None if description.shader == Some(ShaderType::Sun) => Some(Emission {
+                        color: Vec3::new(1.0, 1.0, 1.0),
+                        intensity: 1.0,
+                        falloff_distance: file.lighting.falloff_distance,
+                    }),
+                    None => None,
+                },
+                rotation_speed: description.rotation_speed,
+                warp_distance: description.warp_distance.unwrap_or(description.scale.max(10.0) * 3.0),
+                name: description.name,
+                color: description.color,
+                parent,
+            })
+            .collect();
+
+        let mut children = vec![Vec::new(); bodies.len()];
+        let mut roots = Vec::new();
+        for (index, body) in bodies.iter().enumerate() {
+            match body.parent {
+                Some(parent) => children[parent].push(index),
+                None => roots.push(index),
+            }
+        }
+
+        let post_process = file
+            .post_process
+            .iter()
+            .map(|description| PostEffect::load(description, base_dir))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut system = SolarSystem {
+            // Centro del sistema solar
+            sun_center: Vec3::new(0.0, 0.0, 0.0),
+            time: 0.0,
+            physics: file.physics,
+            lighting: file.lighting,
+            hdr: file.hdr,
+            bloom: file.bloom,
+            post_process,
+            orbits: file.orbits,
+            lights,
+            world_transforms: vec![Mat4::identity(); bodies.len()],
+            bodies,
+            children,
+            roots,
+            nbody: None,
+        };
+
+        // Un ciclo de padres deja cuerpos sin alcanzar desde las raíces
+        if let Some(unreachable) = system.unreachable_body() {
+            return Err(format!("{}: la cadena de padres forma un ciclo", system.bodies[unreachable].name));
+        }
+
+        system.update_transforms();
+        if system.physics.enabled {
+            system.set_mode(SimulationMode::NBody);
+        }
+        Ok(system)
+    }
+
+    fn unreachable_body(&self) -> Option<usize> {
+        let mut visited = vec![false; self.bodies.len()];
+        let mut stack = self.roots.clone();
+        while let Some(index) = stack.pop() {
+            visited[index] = true;
+            stack.extend(&self.children[index]);
+        }
+        visited.iter().position(|&v| !v)
+    }
+
+    pub fn update(&mut self, delta_time: f32) {
+        self.time += delta_time;
+        for body in &mut self.bodies {
+            body.rotation.y += body.rotation_speed * delta_time;
+            if let Some(clouds) = &mut body.clouds {
+                clouds.rotation += clouds.rotation_speed * delta_time;
+            }
+        }
+
+        match &mut self.nbody {
+            None => self.update_transforms(),
+            Some(nbody) => {
+                nbody.advance(delta_time);
+                for (transform, position) in self.world_transforms.iter_mut().zip(&nbody.positions) {
+                    *transform = nalgebra_glm::translation(position);
+                }
+            }
+        }
+    }
+
+    pub fn mode(&self) -> SimulationMode {
+        match self.nbody {
+            None => SimulationMode::Kepler,
+            Some(_) => SimulationMode::NBody,
+        }
+    }
+
+    // Al pasar a N cuerpos la simulación parte de las posiciones y velocidades actuales;
+    // al volver a Kepler los cuerpos regresan a sus órbitas cinemáticas
+    pub fn set_mode(&mut self, mode: SimulationMode) {
+        match mode {
+            SimulationMode::Kepler => {
+                self.nbody = None;
+                self.update_transforms();
+            }
+            SimulationMode::NBody => {
+                let positions = (0..self.bodies.len()).map(|index| self.body_position(index)).collect();
+                let velocities = self.world_velocities();
+                let masses = self.bodies.iter().map(|body| body.mass).collect();
+                self.nbody = Some(NBody::new(positions, velocities, masses, self.physics));
+            }
+        }
+    }
+
+    // Velocidades de mundo a partir de los elementos orbitales, sumadas por la cadena de padres.
+    // Si el padre tiene masa, el movimiento medio sale de la gravedad (n² a³ = G (M + m)) para
+    // que la órbita sea estable en modo N cuerpos.
+    fn world_velocities(&self) -> Vec<Vec3> {
+        let mut velocities = vec![Vec3::zeros(); self.bodies.len()];
+        let mut stack: Vec<(usize, Vec3)> = self.roots.iter().map(|&root| (root, Vec3::zeros())).collect();
+
+        while let Some((index, parent_velocity)) = stack.pop() {
+            let body = &self.bodies[index];
+            let mut orbit = body.orbit;
+            let central_mass = body.parent.map_or(0.0, |parent| self.bodies[parent].mass);
+            if central_mass > 0.0 && orbit.semi_major_axis > 0.0 {
+                let gm = self.physics.gravitational_constant * (central_mass + body.mass);
+                orbit.mean_motion = (gm / orbit.semi_major_axis.powi(3)).sqrt();
+            }
+
+            let velocity = parent_velocity + orbit.velocity(body.orbit.mean_anomaly_at(self.time));
+            velocities[index] = velocity;
+            stack.extend(self.children[index].iter().map(|&child| (child, velocity)));
+        }
+        velocities
+    }
+
+    // Aceleración gravitacional sobre un objeto sin masa (la nave); cero en modo Kepler
+    pub fn gravity_at(&self, point: Vec3) -> Vec3 {
+        match &self.nbody {
+            None => Vec3::zeros(),
+            Some(nbody) => nbody.gravity_at(point, None),
+        }
+    }
+
+    // Recorre el grafo desde las raíces componiendo padre * órbita local
+    fn update_transforms(&mut self) {
+        let root_transform = nalgebra_glm::translation(&self.sun_center);
+        let mut stack: Vec<(usize, Mat4)> = self.roots.iter().map(|&root| (root, root_transform)).collect();
+
+        while let Some((index, parent_transform)) = stack.pop() {
+            let world = parent_transform * self.bodies[index].local_transform(self.time);
+            self.world_transforms[index] = world;
+            stack.extend(self.children[index].iter().map(|&child| (child, world)));
+        }
+    }
+
+    pub fn world_transform(&self, index: usize) -> &Mat4 {
+        &self.world_transforms[index]
+    }
+
+    pub fn body_position(&self, index: usize) -> Vec3 {
+        let origin = self.world_transforms[index] * Vec4::new(0.0, 0.0, 0.0, 1.0);
+        Vec3::new(origin.x, origin.y, origin.z)
+    }
+
+    // Luces de la escena más una luz puntual en la posición de cada cuerpo emisivo
+    pub fn lights(&self) -> Vec<Light> {
+        let mut lights = self.lights.clone();
+        for (index, body) in self.bodies.iter().enumerate() {
+            if let Some(emission) = &body.emission {
+                lights.push(emission.light_at(self.body_position(index), body.scale));
+            }
+        }
+        lights
+    }
+
+    // Esferas que proyectan sombra (cuerpos visibles, opacos y esféricos), menos `excluding`
+    pub fn occluders(&self, excluding: Option<usize>) -> Vec<Occluder> {
+        self.bodies
+            .iter()
+            .enumerate()
+            .filter(|&(index, body)| {
+                Some(index) != excluding && body.is_visible() && body.model == BodyModel::Sphere && body.blend_mode.is_none()
+            })
+            .map(|(index, body)| Occluder { center: self.body_position(index), radius: body.scale })
+            .collect()
+    }
+
+    // Centro de la órbita: el padre del cuerpo, o el centro del sistema
+    pub fn orbit_center(&self, index: usize) -> Vec3 {
+        match self.bodies[index].parent {
+            Some(parent) => self.body_position(parent),
+            None => self.sun_center,
+        }
+    }
+
+    pub fn resolve_collisions(&self, camera: &mut Camera) {
+        for (index, body) in self.bodies.iter().enumerate() {
+            if !body.is_visible() {
+                continue;
+            }
+            let world_pos = self.body_position(index);
+            if camera.check_collision(world_pos, body.scale) {
+                camera.resolve_collision(world_pos, body.scale);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> Result<SolarSystem, String> {
+        SolarSystem::from_toml(source, Path::new("."))
+    }
+
+    fn parse_error(source: &str) -> String {
+        parse(source).err().expect("la escena debería ser rechazada")
+    }
+
+    #[test]
+    fn minimal_scene_loads() {
+        let system = parse("[[bodies]]\nname = \"Sol\"\nshader = \"Sun\"\nscale = 10.0\n").unwrap();
+        assert_eq!(system.bodies.len(), 1);
+        assert_eq!(system.bodies[0].name, "Sol");
+    }
+
+    #[test]
+    fn circular_orbit_aliases() {
+        let system = parse(
+            "[[bodies]]\nname = \"Planeta\"\norbital_radius = 120.0\norbital_angle = 0.5\norbital_speed = 0.2\n",
+        )
+        .unwrap();
+        let orbit = system.bodies[0].orbit;
+        assert_eq!(orbit.semi_major_axis, 120.0);
+        assert_eq!(orbit.mean_anomaly_at_epoch, 0.5);
+        assert_eq!(orbit.mean_motion, 0.2);
+    }
+
+    #[test]
+    fn rejects_unknown_fields() {
+        assert!(parse_error("[[bodies]]\nname = \"Sol\"\nradio = 3.0\n").contains("radio"));
+        assert!(parse_error("[fisica]\n[[bodies]]\nname = \"Sol\"\n").contains("fisica"));
+    }
+
+    #[test]
+    fn rejects_duplicate_names() {
+        let error = parse_error("[[bodies]]\nname = \"Sol\"\n[[bodies]]\nname = \"Sol\"\n");
+        assert!(error.contains("repetido"), "{}", error);
+    }
+
+    #[test]
+    fn rejects_invalid_body_values() {
+        let cases = [
+            ("eccentricity = 1.0", "eccentricity"),
+            ("eccentricity = -0.1", "eccentricity"),
+            ("mass = -1.0", "mass"),
+            ("shader_params = { opacity = 1.5 }", "opacity"),
+            ("shader_params = { opacity = -0.5 }", "opacity"),
+        ];
+        for (field, expected) in cases {
+            let error = parse_error(&format!("[[bodies]]\nname = \"Sol\"\n{}\n", field));
+            assert!(error.contains(expected), "{}: {}", field, error);
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_parent() {
+        let error = parse_error("[[bodies]]\nname = \"Luna\"\nparent = \"Tierra\"\n");
+        assert!(error.contains("Tierra") && error.contains("no existe"), "{}", error);
+    }
+
+    #[test]
+    fn rejects_parent_cycles() {
+        let error = parse_error(
+            "[[bodies]]\nname = \"Sol\"\n\
+             [[bodies]]\nname = \"A\"\nparent = \"B\"\n\
+             [[bodies]]\nname = \"B\"\nparent = \"A\"\n",
+        );
+        assert!(error.contains("ciclo"), "{}", error);
+        assert!(parse_error("[[bodies]]\nname = \"A\"\nparent = \"A\"\n").contains("ciclo"));
+    }
+
+    #[test]
+    fn parents_may_be_declared_later() {
+        let system = parse("[[bodies]]\nname = \"Luna\"\nparent = \"Tierra\"\n[[bodies]]\nname = \"Tierra\"\n").unwrap();
+        assert_eq!(system.roots, vec![1]);
+        assert_eq!(system.children[1], vec![0]);
+    }
+}
@@ -1,8 +1,6 @@
-use nalgebra_glm::{Vec3, Vec4, Mat3};
+use nalgebra_glm::{Vec2, Vec3};
 use crate::color::Color;
 use std::f32::consts::PI;
-use crate::vertex::Vertex;
-use crate::Uniforms;
 use crate::fragment::Fragment;
 
 // ============= FUNCIONES DE RUIDO PROCEDURAL =============
@@ -44,6 +42,61 @@ fn fbm(x: f32, y: f32, octaves: u32) -> f32 {
     value
 }
 
+pub(crate) fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+// fbm sin costuras sobre una esfera: mezcla el ruido 2D de los tres planos de ejes
+// según cuánto mira `direction` hacia cada uno
+pub fn cloud_noise(position: &Vec3, direction: &Vec3, octaves: u32) -> f32 {
+    let weights = direction.abs().map(|w| w * w);
+    let weights = weights / (weights.x + weights.y + weights.z);
+    fbm(position.y, position.z, octaves) * weights.x
+        + fbm(position.z, position.x, octaves) * weights.y
+        + fbm(position.x, position.y, octaves) * weights.z
+}
+
+// ============= RELIEVE (BUMP MAPPING) =============
+
+// Perturba la normal con el gradiente de un campo de alturas (diferencias finitas).
+// Solo cuenta la parte del gradiente tangente a la superficie. Las alturas se atenúan
+// con sin²(φ) para que el gradiente no diverja en los polos del mapeo esférico.
+pub fn bump_normal(normal: &Vec3, position: &Vec3, strength: f32, height: impl Fn(&Vec3) -> f32) -> Vec3 {
+    const EPSILON: f32 = 0.002;
+    let h = height(position);
+    let gradient = Vec3::new(
+        height(&(position + Vec3::new(EPSILON, 0.0, 0.0))) - h,
+        height(&(position + Vec3::new(0.0, EPSILON, 0.0))) - h,
+        height(&(position + Vec3::new(0.0, 0.0, EPSILON))) - h,
+    ) / EPSILON;
+
+    let normal = normal.normalize();
+    let tangential = gradient - normal * gradient.dot(&normal);
+    (normal - tangential * strength).normalize()
+}
+
+// Alturas de los continentes del planeta rocoso: los océanos son planos y las
+// montañas salen del mismo fbm de elevación que usa el color
+pub fn rocky_planet_height(position: &Vec3, time: f32) -> f32 {
+    let theta = position.y.atan2(position.x) + time * 0.1;
+    let phi = (position.z / position.magnitude()).acos();
+
+    let land = smoothstep(0.48, 0.55, fbm(theta * 3.0, phi * 3.0, 5));
+    let elevation = fbm(theta * 10.0, phi * 10.0, 3);
+    land * (0.01 + elevation * 0.02) * phi.sin().powi(2)
+}
+
+// Cráteres de la luna: hundimientos donde el ruido supera el umbral, con el borde abrupto
+pub fn moon_height(position: &Vec3) -> f32 {
+    let theta = position.y.atan2(position.x);
+    let phi = (position.z / position.magnitude()).acos();
+
+    // Solo las octavas grandes: las finas quedan por debajo del tamaño de un píxel
+    let crater_noise = fbm(theta * 15.0, phi * 15.0, 2);
+    (crater_noise * 0.008 - smoothstep(0.62, 0.72, crater_noise) * 0.02) * phi.sin().powi(2)
+}
+
 // ============= SHADER 1: SOL (ESTRELLA) =============
 // Capas: gradiente radial, manchas solares, llamaradas, corona
 pub fn sun_shader(position: &Vec3, time: f32) -> Color {
@@ -94,7 +147,7 @@ pub fn sun_shader(position: &Vec3, time: f32) -> Color {
 }
 
 // ============= SHADER 2: PLANETA ROCOSO (TIPO TIERRA/MARTE) =============
-// Capas: continentes, océanos, nubes, casquetes polares
+// Capas: continentes, océanos, casquetes polares (las nubes son una capa aparte, ver clouds.rs)
 pub fn rocky_planet_shader(position: &Vec3, time: f32) -> Color {
     // Usa coordenadas esféricas para mapeo consistente
     let theta = position.y.atan2(position.x) + time * 0.1; // Rotación lenta
@@ -107,11 +160,7 @@ pub fn rocky_planet_shader(position: &Vec3, time: f32) -> Color {
     // Capa 2: Variación de elevación en continentes
     let elevation = fbm(theta * 10.0, phi * 10.0, 3);
     
-    // Capa 3: Nubes
-    let cloud_noise = fbm(theta * 5.0 - time * 0.5, phi * 5.0, 3);
-    let clouds = if cloud_noise > 0.6 { 0.3 } else { 0.0 };
-    
-    // Capa 4: Casquetes polares
+    // Capa 3: Casquetes polares
     let pole_factor = (phi / PI).abs();
     let is_pole = pole_factor < 0.15 || pole_factor > 0.85;
     
@@ -123,10 +172,9 @@ pub fn rocky_planet_shader(position: &Vec3, time: f32) -> Color {
     let forest = Color::new(30, 100, 40);       // Verde oscuro
     let mountain = Color::new(120, 120, 120);   // Gris montañas
     let snow = Color::new(240, 250, 255);       // Blanco nieve
-    let white_cloud = Color::new(255, 255, 255);
     
     // Determina el color base
-    let base_color = if is_pole {
+    if is_pole {
         snow
     } else if is_land {
         // Tierra con variación de elevación
@@ -146,12 +194,7 @@ pub fn rocky_planet_shader(position: &Vec3, time: f32) -> Color {
         } else {
             ocean
         }
-    };
-    
-    // Aplica nubes
-    let final_color = base_color * (1.0 - clouds) + white_cloud * clouds;
-    
-    final_color
+    }
 }
 
 // ============= SHADER 3: GIGANTE GASEOSO (TIPO JÚPITER) =============
@@ -240,21 +283,19 @@ pub fn ice_planet_shader(position: &Vec3, time: f32) -> Color {
 }
 
 // ============= SHADER 5: PLANETA VOLCÁNICO (BONUS - TIPO IO) =============
+// Cuánta luz propia emite la lava, además de la que refleja
+const LAVA_GLOW: f32 = 2.0;
+
 pub fn volcanic_planet_shader(position: &Vec3, time: f32) -> Color {
     let theta = position.y.atan2(position.x);
     let phi = (position.z / (position.x * position.x + position.y * position.y + position.z * position.z).sqrt()).acos();
     
-    // Lava activa (animada)
-    let lava_flow = fbm(theta * 5.0 + time * 2.0, phi * 5.0 + time, 3);
-    
     // Roca volcánica
     let rock_texture = fbm(theta * 10.0, phi * 10.0, 4);
     
     // Colores
     let black_rock = Color::new(40, 30, 30);
     let gray_rock = Color::new(80, 70, 70);
-    let lava_orange = Color::new(255, 120, 30);
-    let lava_yellow = Color::new(255, 200, 50);
     
     let rock_color = if rock_texture > 0.5 {
         gray_rock
@@ -262,39 +303,51 @@ pub fn volcanic_planet_shader(position: &Vec3, time: f32) -> Color {
         black_rock
     };
     
-    // Lava activa
-    let lava_intensity = (lava_flow * 0.5 + 0.5).clamp(0.0, 1.0);
-    let is_lava = lava_flow > 0.6;
+    lava(position, time).unwrap_or(rock_color)
+}
+
+// Luz que emite la lava: brilla también en el lado nocturno y resplandece con el bloom
+pub fn volcanic_planet_emission(position: &Vec3, time: f32) -> Option<Color> {
+    lava(position, time).map(|color| color * LAVA_GLOW)
+}
+
+// Color de la lava activa (animada) en `position`, o None sobre la roca
+fn lava(position: &Vec3, time: f32) -> Option<Color> {
+    let theta = position.y.atan2(position.x);
+    let phi = (position.z / (position.x * position.x + position.y * position.y + position.z * position.z).sqrt()).acos();
     
-    if is_lava {
-        let lava_color = lava_orange * (1.0 - lava_intensity) + lava_yellow * lava_intensity;
-        lava_color * (0.8 + (time * 5.0).sin() * 0.2)
-    } else {
-        rock_color
+    let lava_flow = fbm(theta * 5.0 + time * 2.0, phi * 5.0 + time, 3);
+    if lava_flow <= 0.6 {
+        return None;
     }
+    
+    let lava_orange = Color::new(255, 120, 30);
+    let lava_yellow = Color::new(255, 200, 50);
+    let lava_intensity = (lava_flow * 0.5 + 0.5).clamp(0.0, 1.0);
+    let lava_color = lava_orange * (1.0 - lava_intensity) + lava_yellow * lava_intensity;
+    Some(lava_color * (0.8 + (time * 5.0).sin() * 0.2))
 }
 
 // ============= SHADER 6: ANILLOS (PARA GIGANTES GASEOSOS) =============
-pub fn ring_shader(position: &Vec3, distance_from_center: f32) -> Color {
+pub fn ring_shader(position: &Vec3, distance_from_center: f32) -> Option<Color> {
     // Los anillos son un plano alrededor del planeta
     // distance_from_center es la distancia radial en el plano XZ
+    // Devuelve None en los gaps, que se descartan; las bandas son translúcidas
     
     // Múltiples anillos con gaps
     let ring_pattern = (distance_from_center * 20.0).sin();
     let ring_noise = fbm(distance_from_center * 30.0, position.y * 50.0, 3);
     
-    // Colores de anillos
-    let light_ring = Color::new(200, 180, 160);
-    let dark_ring = Color::new(120, 110, 100);
-    let gap = Color::new(0, 0, 0); // Transparente (negro)
-    
+    // Colores de anillos (las bandas claras son más densas)
+    let light_ring = Color::new_rgba(200, 180, 160, 230);
+    let dark_ring = Color::new_rgba(120, 110, 100, 180);
     // Determina si es gap o anillo
     if ring_pattern > 0.8 {
-        gap
+        None
     } else if ring_noise > 0.6 {
-        light_ring * 0.8
+        Some(light_ring * 0.8)
     } else {
-        dark_ring * 0.6
+        Some(dark_ring * 0.6)
     }
 }
 
@@ -320,46 +373,6 @@ pub fn moon_shader(position: &Vec3) -> Color {
     }
 }
 
-// ============= SHADER NAVE ESPACIAL =============
-pub fn vertex_shader(vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
-    // Transform position
-    let position = Vec4::new(
-        vertex.position.x,
-        vertex.position.y,
-        vertex.position.z,
-        1.0
-    );
-    let transformed = uniforms.model_matrix * position;
-
-    // Perform perspective division
-    let w = transformed.w;
-    let transformed_position = Vec3::new(
-        transformed.x / w,
-        transformed.y / w,
-        transformed.z / w
-    );
-
-    // Transform normal
-    let model_mat3 = Mat3::new(
-        uniforms.model_matrix[0], uniforms.model_matrix[1], uniforms.model_matrix[2],
-        uniforms.model_matrix[4], uniforms.model_matrix[5], uniforms.model_matrix[6],
-        uniforms.model_matrix[8], uniforms.model_matrix[9], uniforms.model_matrix[10]
-    );
-    let normal_matrix = model_mat3.transpose().try_inverse().unwrap_or(Mat3::identity());
-    
-    let transformed_normal = normal_matrix * vertex.normal;
-
-    // Create a new Vertex with transformed attributes
-    Vertex {
-        position: vertex.position,
-        normal: vertex.normal,
-        tex_coords: vertex.tex_coords,
-        color: vertex.color,
-        transformed_position,
-        transformed_normal,
-    }
-}
-
 pub fn fragment_shader(fragment: &Fragment, light_dir: &Vec3, normal: &Vec3) -> Color {
     // Iluminación difusa
     let normal = normal.normalize();
@@ -427,6 +440,29 @@ pub fn spaceship_shader(position: &Vec3, normal: &Vec3, light_dir: &Vec3) -> Col
     lit_color + rim_color
 }
 
+// Toberas en la cara trasera del casco (el morro apunta a +Z en el modelo): un núcleo
+// blanco que se vuelve azul hacia el borde, bastante más brillante que el blanco de referencia
+const ENGINE_CENTERS: [f32; 2] = [-0.5, 0.5];
+const ENGINE_RADIUS: f32 = 0.4;
+const ENGINE_GLOW: f32 = 4.0;
+
+pub fn spaceship_engine_emission(position: &Vec3) -> Option<Color> {
+    // Solo la cara trasera del casco central; las alas se extienden más atrás pero con |x| > 1
+    if position.z > -1.35 || position.x.abs() > 1.0 {
+        return None;
+    }
+    let distance = ENGINE_CENTERS
+        .iter()
+        .map(|center| Vec2::new(position.x - center, position.y).magnitude())
+        .fold(f32::INFINITY, f32::min);
+    if distance >= ENGINE_RADIUS {
+        return None;
+    }
+    let t = distance / ENGINE_RADIUS;
+    let color = Color::new(255, 255, 255).lerp(&Color::new(90, 150, 255), t);
+    Some(color * (ENGINE_GLOW * (1.0 - t * t)))
+}
+
 // Shader de nave de guerra (rojo/naranja)
 pub fn warship_shader(position: &Vec3, normal: &Vec3, light_dir: &Vec3) -> Color {
     let normal = normal.normalize();
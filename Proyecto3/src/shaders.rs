@@ -1,4 +1,4 @@
-use nalgebra_glm::{Vec3, Vec4, Mat3};
+use nalgebra_glm::{Vec3, Vec4};
 use crate::vertex::Vertex;
 use crate::Uniforms;
 
@@ -10,33 +10,41 @@ pub fn vertex_shader(vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
     vertex.position.z,
     1.0
   );
-  let transformed = uniforms.model_matrix * position;
+
+  let world_position = uniforms.model_matrix * position;
+
+  // Model -> View -> Projection (clip space)
+  let clip_position = uniforms.projection_matrix * uniforms.view_matrix * world_position;
 
   // Perform perspective division
-  let w = transformed.w;
-  let transformed_position = Vec3::new(
-    transformed.x / w,
-    transformed.y / w,
-    transformed.z / w
+  let w = clip_position.w;
+  let ndc_position = Vec4::new(
+    clip_position.x / w,
+    clip_position.y / w,
+    clip_position.z / w,
+    1.0
   );
 
-  // Transform normal
-
-  let model_mat3 = Mat3::new(
-    uniforms.model_matrix[0], uniforms.model_matrix[1], uniforms.model_matrix[2],
-    uniforms.model_matrix[4], uniforms.model_matrix[5], uniforms.model_matrix[6],
-    uniforms.model_matrix[8], uniforms.model_matrix[9], uniforms.model_matrix[10]
+  // NDC -> coordenadas de pantalla (z se conserva como profundidad en [-1, 1])
+  let screen_position = uniforms.viewport_matrix * ndc_position;
+  let transformed_position = Vec3::new(
+    screen_position.x,
+    screen_position.y,
+    screen_position.z
   );
-  let normal_matrix = model_mat3.transpose().try_inverse().unwrap_or(Mat3::identity());
 
-  let transformed_normal = normal_matrix * vertex.normal;
+  // Transform normal (ver create_normal_matrix)
+  let transformed_normal = uniforms.normal_matrix * vertex.normal;
 
   // Create a new Vertex with transformed attributes
   Vertex {
     position: vertex.position,
     normal: vertex.normal,
+    tangent: vertex.tangent,
     tex_coords: vertex.tex_coords,
     color: vertex.color,
+    world_position: Vec3::new(world_position.x, world_position.y, world_position.z),
+    clip_position,
     transformed_position,
     transformed_normal,
   }
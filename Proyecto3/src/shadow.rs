@@ -0,0 +1,114 @@
+// shadow.rs - Sombras analíticas entre cuerpos esféricos (eclipses y tránsitos)
+//
+// Visto desde un fragmento, la luz es un disco de radio angular asin(R / d) y cada cuerpo
+// entre el fragmento y la luz es otro disco. La fracción del disco de la luz que queda
+// descubierta da la umbra (0), la penumbra y la antumbra. Las luces sin radio dan sombras
+// duras.
+
+use std::f32::consts::PI;
+use nalgebra_glm::Vec3;
+
+// Esfera que proyecta sombra (un cuerpo de la escena)
+#[derive(Clone, Copy, Debug)]
+pub struct Occluder {
+    pub center: Vec3,
+    pub radius: f32,
+}
+
+// Fracción de la luz que llega a `point` (0 = umbra, 1 = sin sombra).
+// `light_distance` es infinita para las luces direccionales.
+pub fn light_visibility(point: &Vec3, light_dir: &Vec3, light_distance: f32, light_radius: f32, occluders: &[Occluder]) -> f32 {
+    let light_angle = if light_distance.is_finite() {
+        (light_radius / light_distance).min(1.0).asin()
+    } else {
+        0.0
+    };
+
+    let mut visibility = 1.0;
+    for occluder in occluders {
+        let to_occluder = occluder.center - point;
+        let along = to_occluder.dot(light_dir);
+        if along <= 0.0 {
+            continue; // Detrás del fragmento
+        }
+        let distance = to_occluder.magnitude();
+        // El propio cuerpo del fragmento (las caras de la malla quedan dentro de la esfera)
+        // y la estrella que emite la luz no se tapan a sí mismos
+        if distance <= occluder.radius * 1.01 || along >= light_distance - occluder.radius {
+            continue;
+        }
+
+        let occluder_angle = (occluder.radius / distance).asin();
+        let separation = (along / distance).clamp(-1.0, 1.0).acos();
+        visibility *= 1.0 - covered_fraction(light_angle, occluder_angle, separation);
+        if visibility <= 0.0 {
+            return 0.0;
+        }
+    }
+    visibility
+}
+
+// Fracción de un disco de radio `a` tapada por otro de radio `b` con centros a distancia `c`
+fn covered_fraction(a: f32, b: f32, c: f32) -> f32 {
+    if c >= a + b {
+        return 0.0;
+    }
+    if a <= 1e-6 {
+        return 1.0; // Luz puntual dentro del disco del cuerpo
+    }
+    if c <= (a - b).abs() {
+        // Un disco dentro del otro: eclipse total o anular
+        return (a.min(b) / a).powi(2);
+    }
+
+    // Área de la lente donde se cruzan los dos círculos
+    let (a2, b2, c2) = (a * a, b * b, c * c);
+    let alpha = ((c2 + a2 - b2) / (2.0 * c * a)).clamp(-1.0, 1.0).acos();
+    let beta = ((c2 + b2 - a2) / (2.0 * c * b)).clamp(-1.0, 1.0).acos();
+    let kite = ((-c + a + b) * (c + a - b) * (c - a + b) * (c + a + b)).max(0.0).sqrt();
+    let lens = a2 * alpha + b2 * beta - 0.5 * kite;
+    (lens / (PI * a2)).clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn covered_fraction_without_overlap_is_zero() {
+        assert_eq!(covered_fraction(1.0, 0.5, 1.5), 0.0);
+        assert_eq!(covered_fraction(1.0, 0.5, 3.0), 0.0);
+    }
+
+    #[test]
+    fn covered_fraction_total_and_annular() {
+        assert_eq!(covered_fraction(1.0, 2.0, 0.5), 1.0);
+        assert_eq!(covered_fraction(0.0, 0.5, 0.2), 1.0);
+        assert!((covered_fraction(1.0, 0.5, 0.3) - 0.25).abs() < 1e-6);
+    }
+
+    #[test]
+    fn covered_fraction_partial_overlap() {
+        // Dos discos iguales a un radio de distancia: lente de 2π/3 - √3/2
+        let expected = (2.0 * PI / 3.0 - 3.0_f32.sqrt() / 2.0) / PI;
+        assert!((covered_fraction(1.0, 1.0, 1.0) - expected).abs() < 1e-5);
+
+        // Continua en los bordes y decreciente al separarse
+        assert!((covered_fraction(1.0, 0.5, 0.5 + 1e-4) - 0.25).abs() < 1e-3);
+        assert!(covered_fraction(1.0, 0.5, 1.5 - 1e-4) < 1e-3);
+        let samples: Vec<f32> = (0..=20).map(|i| covered_fraction(1.0, 0.5, 0.5 + i as f32 * 0.05)).collect();
+        assert!(samples.windows(2).all(|pair| pair[1] <= pair[0]));
+    }
+
+    #[test]
+    fn occluder_between_point_and_light_casts_shadow() {
+        let light_dir = Vec3::new(1.0, 0.0, 0.0);
+        let occluder = Occluder { center: Vec3::new(5.0, 0.0, 0.0), radius: 1.0 };
+        assert_eq!(light_visibility(&Vec3::zeros(), &light_dir, 10.0, 0.0, &[occluder]), 0.0);
+
+        // Detrás del fragmento o fuera de la línea de la luz no tapa nada
+        let behind = Occluder { center: Vec3::new(-5.0, 0.0, 0.0), radius: 1.0 };
+        let aside = Occluder { center: Vec3::new(5.0, 3.0, 0.0), radius: 1.0 };
+        assert_eq!(light_visibility(&Vec3::zeros(), &light_dir, 10.0, 0.0, &[behind, aside]), 1.0);
+    }
+}
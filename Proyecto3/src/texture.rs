@@ -0,0 +1,181 @@
+// texture.rs - Texturas de imagen (PNG/JPEG) con mipmaps, filtrado y modos de borde
+
+use std::path::Path;
+use std::sync::Arc;
+use nalgebra_glm::{Vec2, Vec3};
+use serde::Deserialize;
+use crate::color::{srgb_to_linear, Color};
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default, Deserialize)]
+pub enum FilterMode {
+    Nearest,
+    // Bilineal dentro de cada nivel y lineal entre niveles de mipmap (trilineal)
+    #[default]
+    Bilinear,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default, Deserialize)]
+pub enum WrapMode {
+    #[default]
+    Repeat,
+    Clamp,
+}
+
+// Cómo se combina la textura con el color del shader procedural
+#[derive(Clone, Copy, PartialEq, Debug, Default, Deserialize)]
+pub enum TextureMode {
+    #[default]
+    Replace,
+    Modulate,
+}
+
+// Textura asignada a una superficie (compartida entre cuerpos y hilos del rasterizador)
+#[derive(Clone)]
+pub struct SurfaceTexture {
+    pub texture: Arc<Texture>,
+    pub mode: TextureMode,
+}
+
+// Normal map en espacio tangente (RGB = XYZ en [-1, 1], convención OpenGL)
+#[derive(Clone)]
+pub struct NormalMap {
+    pub texture: Arc<Texture>,
+    pub strength: f32,
+}
+
+struct MipLevel {
+    width: usize,
+    height: usize,
+    texels: Vec<Vec3>, // RGB en [0, 1]
+}
+
+impl MipLevel {
+    // Reduce a la mitad promediando bloques de 2x2 (los bordes impares repiten el último texel)
+    fn downsample(&self) -> MipLevel {
+        let width = (self.width / 2).max(1);
+        let height = (self.height / 2).max(1);
+        let mut texels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let x0 = (x * 2).min(self.width - 1);
+                let x1 = (x * 2 + 1).min(self.width - 1);
+                let y0 = (y * 2).min(self.height - 1);
+                let y1 = (y * 2 + 1).min(self.height - 1);
+                let sum = self.texel(x0, y0) + self.texel(x1, y0) + self.texel(x0, y1) + self.texel(x1, y1);
+                texels.push(sum * 0.25);
+            }
+        }
+        MipLevel { width, height, texels }
+    }
+
+    fn texel(&self, x: usize, y: usize) -> Vec3 {
+        self.texels[y * self.width + x]
+    }
+}
+
+pub struct Texture {
+    levels: Vec<MipLevel>,
+    pub filter: FilterMode,
+    pub wrap: WrapMode,
+}
+
+impl Texture {
+    pub fn load(path: &Path, filter: FilterMode, wrap: WrapMode) -> Result<Self, String> {
+        let image = image::open(path)
+            .map_err(|e| format!("no se pudo cargar la textura {}: {}", path.display(), e))?
+            .to_rgb8();
+        let (width, height) = image.dimensions();
+        let texels = image
+            .pixels()
+            .map(|p| Vec3::new(p[0] as f32, p[1] as f32, p[2] as f32) / 255.0)
+            .collect();
+        Ok(Texture::from_texels(width as usize, height as usize, texels, filter, wrap))
+    }
+
+    pub fn from_texels(width: usize, height: usize, texels: Vec<Vec3>, filter: FilterMode, wrap: WrapMode) -> Self {
+        assert_eq!(texels.len(), width * height, "tamaño de textura inconsistente");
+
+        let mut levels = vec![MipLevel { width, height, texels }];
+        while let Some(last) = levels.last() {
+            if last.width == 1 && last.height == 1 {
+                break;
+            }
+            let next = last.downsample();
+            levels.push(next);
+        }
+
+        Texture { levels, filter, wrap }
+    }
+
+    pub fn width(&self) -> usize {
+        self.levels[0].width
+    }
+
+    pub fn height(&self) -> usize {
+        self.levels[0].height
+    }
+
+    // Nivel de detalle a partir de las derivadas de las UV respecto a x e y de pantalla
+    pub fn level_of_detail(&self, uv_dx: Vec2, uv_dy: Vec2) -> f32 {
+        let size = Vec2::new(self.width() as f32, self.height() as f32);
+        let footprint_x = uv_dx.component_mul(&size).magnitude();
+        let footprint_y = uv_dy.component_mul(&size).magnitude();
+        footprint_x.max(footprint_y).max(1e-6).log2().max(0.0)
+    }
+
+    // Color de la textura en RGB lineal (los texels se guardan en sRGB, como en la imagen)
+    pub fn sample(&self, uv: Vec2, lod: f32) -> Color {
+        let rgb = self.sample_rgb(uv, lod).map(srgb_to_linear);
+        Color::from_float(rgb.x, rgb.y, rgb.z)
+    }
+
+    // Texels sin convertir, tal como están en la imagen (p. ej. las normales de un normal map)
+    pub fn sample_rgb(&self, uv: Vec2, lod: f32) -> Vec3 {
+        let max_level = (self.levels.len() - 1) as f32;
+        let lod = lod.clamp(0.0, max_level);
+
+        match self.filter {
+            FilterMode::Nearest => self.sample_nearest(&self.levels[lod.round() as usize], uv),
+            FilterMode::Bilinear => {
+                let lower = lod.floor();
+                let fine = self.sample_bilinear(&self.levels[lower as usize], uv);
+                if lower < max_level {
+                    let coarse = self.sample_bilinear(&self.levels[lower as usize + 1], uv);
+                    fine + (coarse - fine) * (lod - lower)
+                } else {
+                    fine
+                }
+            }
+        }
+    }
+
+    fn sample_nearest(&self, level: &MipLevel, uv: Vec2) -> Vec3 {
+        let x = self.wrap_index((uv.x * level.width as f32).floor() as i32, level.width);
+        let y = self.wrap_index((uv.y * level.height as f32).floor() as i32, level.height);
+        level.texel(x, y)
+    }
+
+    fn sample_bilinear(&self, level: &MipLevel, uv: Vec2) -> Vec3 {
+        // Centros de texel en coordenadas +0.5
+        let fx = uv.x * level.width as f32 - 0.5;
+        let fy = uv.y * level.height as f32 - 0.5;
+        let (x0, y0) = (fx.floor(), fy.floor());
+        let (tx, ty) = (fx - x0, fy - y0);
+
+        let xa = self.wrap_index(x0 as i32, level.width);
+        let xb = self.wrap_index(x0 as i32 + 1, level.width);
+        let ya = self.wrap_index(y0 as i32, level.height);
+        let yb = self.wrap_index(y0 as i32 + 1, level.height);
+
+        let top = level.texel(xa, ya) * (1.0 - tx) + level.texel(xb, ya) * tx;
+        let bottom = level.texel(xa, yb) * (1.0 - tx) + level.texel(xb, yb) * tx;
+        top * (1.0 - ty) + bottom * ty
+    }
+
+    fn wrap_index(&self, index: i32, size: usize) -> usize {
+        match self.wrap {
+            WrapMode::Repeat => index.rem_euclid(size as i32) as usize,
+            WrapMode::Clamp => index.clamp(0, size as i32 - 1) as usize,
+        }
+    }
+}
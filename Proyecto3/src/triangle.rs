@@ -1,10 +1,16 @@
-use nalgebra_glm::{Vec3, dot};
-use crate::fragment::Fragment;
+use nalgebra_glm::{Vec2, Vec3, Vec4, dot};
+use crate::antialiasing::MAX_SAMPLES;
+use crate::framebuffer::TileRow;
+use crate::rasterizer::PixelRect;
 use crate::vertex::Vertex;
-use crate::color::Color;
+use crate::color::{BlendMode, Color};
+use crate::fragment::Fragment;
+use crate::texture::{NormalMap, TextureMode};
 use crate::shader;
+use crate::Uniforms;
+use serde::Deserialize;
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Debug, Deserialize)]
 pub enum ShaderType {
     Sun,
     RockyPlanet,
@@ -13,98 +19,352 @@ pub enum ShaderType {
     VolcanicPlanet,
     Moon,
     Spaceship,
+    // Para la malla de los anillos (ver ring.rs)
+    Ring,
+    // Para la esfera de la atmósfera (ver atmosphere.rs)
+    Atmosphere,
+    // Para la esfera de nubes (ver clouds.rs)
+    Clouds,
+}
+
+// Parámetros por cuerpo que modifican la entrada/salida del shader procedural
+#[derive(Clone, Copy, PartialEq, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ShaderParams {
+    pub scale: f32,      // Escala del patrón
+    pub speed: f32,      // Multiplicador del tiempo de animación
+    pub seed: f32,       // Desplazamiento dentro del campo de ruido
+    pub brightness: f32, // Multiplicador del color final
+    pub bump: f32,       // Intensidad del relieve procedural (0 = liso)
+    pub opacity: f32,    // Multiplica la opacidad del shader (solo en la pasada transparente)
+}
+
+impl Default for ShaderParams {
+    fn default() -> Self {
+        ShaderParams {
+            scale: 1.0,
+            speed: 1.0,
+            seed: 0.0,
+            brightness: 1.0,
+            bump: 1.0,
+            opacity: 1.0,
+        }
+    }
+}
+
+// Qué caras descartar antes de rasterizar. Las caras frontales son las que
+// aparecen en sentido antihorario desde la cámara (área con signo positiva).
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum CullMode {
+    None,
+    Back,
+    Front,
+}
+
+pub fn is_culled(v1: &Vertex, v2: &Vertex, v3: &Vertex, cull_mode: CullMode) -> bool {
+    let area = edge_function(&v1.transformed_position, &v2.transformed_position, &v3.transformed_position);
+    match cull_mode {
+        CullMode::None => false,
+        CullMode::Back => area <= 0.0,
+        CullMode::Front => area >= 0.0,
+    }
+}
+
+impl CullMode {
+    pub fn next(self) -> Self {
+        match self {
+            CullMode::None => CullMode::Back,
+            CullMode::Back => CullMode::Front,
+            CullMode::Front => CullMode::None,
+        }
+    }
+}
+
+// Estado fijo de una llamada de dibujo
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct RenderState {
+    pub cull_mode: CullMode,
+    // None = pasada opaca (escribe profundidad); Some = pasada transparente
+    pub blend_mode: Option<BlendMode>,
+}
+
+impl RenderState {
+    pub fn opaque(cull_mode: CullMode) -> Self {
+        RenderState { cull_mode, blend_mode: None }
+    }
+
+    pub fn transparent(blend_mode: BlendMode) -> Self {
+        RenderState { cull_mode: CullMode::None, blend_mode: Some(blend_mode) }
+    }
+}
+
+// Bounding box del triángulo en pantalla, recortada a los límites del framebuffer
+pub fn screen_bounds(v1: &Vertex, v2: &Vertex, v3: &Vertex, width: usize, height: usize) -> PixelRect {
+    let screen = (0, 0, width as i32 - 1, height as i32 - 1);
+    calculate_bounding_box(&v1.transformed_position, &v2.transformed_position, &v3.transformed_position, screen)
 }
 
-pub fn triangle(v1: &Vertex, v2: &Vertex, v3: &Vertex, shader_type: ShaderType, time: f32) -> Vec<Fragment> {
-    let mut fragments = Vec::new();
+// Rasteriza el triángulo dentro de `tile` y escribe los fragmentos directo en la franja
+pub fn triangle(vertices: &[Vertex; 3], shader_type: Option<ShaderType>, uniforms: &Uniforms, blend_mode: Option<BlendMode>, target: &mut TileRow, tile: PixelRect) {
+    let [v1, v2, v3] = vertices;
     let (a, b, c) = (v1.transformed_position, v2.transformed_position, v3.transformed_position);
     
-    let (min_x, min_y, max_x, max_y) = calculate_bounding_box(&a, &b, &c);
-    
-    let light_dir = Vec3::new(0.0, 0.0, -1.0);
+    let (min_x, min_y, max_x, max_y) = calculate_bounding_box(&a, &b, &c, tile);
     
     let triangle_area = edge_function(&a, &b, &c);
+    if triangle_area.abs() < f32::EPSILON {
+        return;
+    }
+
+    // 1/w de cada vértice para interpolar con corrección de perspectiva
+    let inv_w = (1.0 / v1.clip_position.w, 1.0 / v2.clip_position.w, 1.0 / v3.clip_position.w);
+    let perspective_weights = |point: &Vec3| {
+        let (w1, w2, w3) = barycentric_coordinates(point, &a, &b, &c, triangle_area);
+        let (p1, p2, p3) = (w1 * inv_w.0, w2 * inv_w.1, w3 * inv_w.2);
+        let sum = p1 + p2 + p3;
+        (p1 / sum, p2 / sum, p3 / sum)
+    };
+    let tex_coords_at = |weights: (f32, f32, f32)| {
+        v1.tex_coords * weights.0 + v2.tex_coords * weights.1 + v3.tex_coords * weights.2
+    };
+    
+    let sample_offsets = target.sample_offsets;
+    // Cada peso es la distancia a la arista opuesta a su vértice; sobre la arista (peso 0)
+    // la muestra solo cuenta si la arista es superior o izquierda
+    let owns_edge = [is_top_left(&b, &c, &a), is_top_left(&c, &a, &b), is_top_left(&a, &b, &c)];
+    let inside = |(w1, w2, w3): (f32, f32, f32)| {
+        [w1, w2, w3].iter().zip(owns_edge).all(|(&w, owns)| w > 0.0 || (w == 0.0 && owns))
+    };
     
     for y in min_y..=max_y {
         for x in min_x..=max_x {
-            let point = Vec3::new(x as f32 + 0.5, y as f32 + 0.5, 0.0);
-            
-            let (w1, w2, w3) = barycentric_coordinates(&point, &a, &b, &c, triangle_area);
+            // Muestras del píxel que cubre el triángulo, con su profundidad
+            // (la z de NDC ya es lineal en pantalla)
+            let mut covered = [(0usize, 0.0f32); MAX_SAMPLES];
+            let mut count = 0;
+            let mut centroid = Vec3::zeros();
+            for (sample, (dx, dy)) in sample_offsets.iter().enumerate() {
+                let position = Vec3::new(x as f32 + 0.5 + dx, y as f32 + 0.5 + dy, 0.0);
+                let (w1, w2, w3) = barycentric_coordinates(&position, &a, &b, &c, triangle_area);
+                if inside((w1, w2, w3)) {
+                    covered[count] = (sample, a.z * w1 + b.z * w2 + c.z * w3);
+                    count += 1;
+                    centroid += position;
+                }
+            }
+            if count == 0 {
+                continue;
+            }
             
-            if w1 >= 0.0 && w1 <= 1.0 &&
-               w2 >= 0.0 && w2 <= 1.0 &&
-               w3 >= 0.0 && w3 <= 1.0 {
-                
-                let normal = v1.transformed_normal * w1 + v2.transformed_normal * w2 + v3.transformed_normal * w3;
-                let normal = normal.normalize();
-                
-                let world_pos = v1.position * w1 + v2.position * w2 + v3.position * w3;
-                
-                let intensity = dot(&normal, &light_dir).max(0.0);
-                
-                let base_color = match shader_type {
-                    ShaderType::Sun => {
-                        shader::sun_shader(&world_pos, time)
-                    },
-                    ShaderType::RockyPlanet => {
-                        let color = shader::rocky_planet_shader(&world_pos, time);
-                        color * intensity.max(0.2)
-                    },
-                    ShaderType::GasGiant => {
-                        let color = shader::gas_giant_shader(&world_pos, time);
-                        color * intensity.max(0.2)
-                    },
-                    ShaderType::IcePlanet => {
-                        let color = shader::ice_planet_shader(&world_pos, time);
-                        color * intensity.max(0.3)
-                    },
-                    ShaderType::VolcanicPlanet => {
-                        let color = shader::volcanic_planet_shader(&world_pos, time);
-                        color * intensity.max(0.4)
-                    },
-                    ShaderType::Moon => {
-                        let color = shader::moon_shader(&world_pos);
-                        color * intensity.max(0.15)
-                    },
-                    ShaderType::Spaceship => {
-                        spaceship_shader(&world_pos, &normal, &light_dir, intensity)
-                    },
-                };
-                
-                let depth = a.z * w1 + b.z * w2 + c.z * w3;
-                
-                fragments.push(Fragment::new(x as f32, y as f32, base_color, depth));
+            // El shader corre una vez por píxel, en el centro de las muestras cubiertas
+            // (dentro del triángulo aunque el centro del píxel quede fuera)
+            let point = centroid / count as f32;
+            let weights = perspective_weights(&point);
+            let mut fragment = interpolate_fragment(v1, v2, v3, weights);
+
+            // Derivadas de las UV con el píxel vecino, para elegir el nivel de mipmap
+            if uniforms.texture.is_some() || uniforms.normal_map.is_some() {
+                let right = tex_coords_at(perspective_weights(&(point + Vec3::new(1.0, 0.0, 0.0))));
+                let below = tex_coords_at(perspective_weights(&(point + Vec3::new(0.0, 1.0, 0.0))));
+                fragment.tex_coords_dx = right - fragment.tex_coords;
+                fragment.tex_coords_dy = below - fragment.tex_coords;
+            }
+
+            // Los fragmentos descartados (gaps de los anillos) no escriben color ni profundidad
+            let Some(color) = shade_fragment(&fragment, shader_type, uniforms) else {
+                continue;
+            };
+            for &(sample, depth) in &covered[..count] {
+                match blend_mode {
+                    None => target.point(x as usize, y as usize, sample, color, depth),
+                    Some(mode) => target.blend(x as usize, y as usize, sample, color, depth, mode),
+                }
             }
         }
     }
-    
-    fragments
+}
+
+// Atributos de los vértices interpolados con pesos ya corregidos por perspectiva
+fn interpolate_fragment(v1: &Vertex, v2: &Vertex, v3: &Vertex, weights: (f32, f32, f32)) -> Fragment {
+    let (w1, w2, w3) = weights;
+    Fragment {
+        color: v1.color * w1 + v2.color * w2 + v3.color * w3,
+        normal: (v1.transformed_normal * w1 + v2.transformed_normal * w2 + v3.transformed_normal * w3).normalize(),
+        object_position: v1.position * w1 + v2.position * w2 + v3.position * w3,
+        object_normal: v1.normal * w1 + v2.normal * w2 + v3.normal * w3,
+        tangent: v1.tangent * w1 + v2.tangent * w2 + v3.tangent * w3,
+        world_position: v1.world_position * w1 + v2.world_position * w2 + v3.world_position * w3,
+        tex_coords: v1.tex_coords * w1 + v2.tex_coords * w2 + v3.tex_coords * w3,
+        tex_coords_dx: Vec2::new(0.0, 0.0),
+        tex_coords_dy: Vec2::new(0.0, 0.0),
+    }
+}
+
+// Etapa de shading: color final de un fragmento según el shader y la textura del cuerpo,
+// o None si el shader lo descarta
+fn shade_fragment(fragment: &Fragment, shader_type: Option<ShaderType>, uniforms: &Uniforms) -> Option<Color> {
+    let params = &uniforms.shader_params;
+    let time = uniforms.time * params.speed;
+
+    let position = fragment.object_position * params.scale + Vec3::repeat(params.seed);
+    let normal = surface_normal(fragment, shader_type, &position, time, uniforms);
+
+    let albedo = match shader_type {
+        Some(ShaderType::Sun) => shader::sun_shader(&position, time),
+        Some(ShaderType::RockyPlanet) => shader::rocky_planet_shader(&position, time),
+        Some(ShaderType::GasGiant) => shader::gas_giant_shader(&position, time),
+        Some(ShaderType::IcePlanet) => shader::ice_planet_shader(&position, time),
+        Some(ShaderType::VolcanicPlanet) => shader::volcanic_planet_shader(&position, time),
+        Some(ShaderType::Moon) => shader::moon_shader(&position),
+        Some(ShaderType::Spaceship) => spaceship_shader(&position),
+        Some(ShaderType::Ring) => {
+            let radial = Vec2::new(fragment.object_position.x, fragment.object_position.z).magnitude();
+            shader::ring_shader(&position, radial * params.scale)?
+        }
+        Some(ShaderType::Atmosphere) => return shade_atmosphere(fragment, uniforms),
+        Some(ShaderType::Clouds) => {
+            let clouds = uniforms.clouds.as_ref()?;
+            let opacity = clouds.density(&fragment.object_position) * clouds.opacity;
+            if opacity <= 0.0 {
+                return None; // Cielo despejado
+            }
+            Color::new(255, 255, 255).fade(opacity)
+        }
+        // Solo textura
+        None => Color::new(255, 255, 255),
+    };
+
+    let albedo = match &uniforms.texture {
+        None => albedo,
+        Some(surface) => {
+            let lod = surface.texture.level_of_detail(fragment.tex_coords_dx, fragment.tex_coords_dy);
+            let texel = surface.texture.sample(fragment.tex_coords, lod);
+            match surface.mode {
+                TextureMode::Replace => texel,
+                TextureMode::Modulate => albedo * texel,
+            }
+        }
+    };
+
+    let albedo = albedo * fragment.color;
+
+    // Las nubes del cuerpo tapan parte de la luz que llega a su superficie
+    let cloud_shadow = match &uniforms.clouds {
+        Some(clouds) if shader_type != Some(ShaderType::Clouds) => Some((
+            clouds,
+            (uniforms.model_matrix * Vec4::new(0.0, 0.0, 0.0, 1.0)).xyz(),
+            (uniforms.model_matrix * Vec4::new(1.0, 0.0, 0.0, 0.0)).xyz().magnitude(),
+        )),
+        _ => None,
+    };
+
+    // Los anillos son una capa de partículas: dispersan la luz por las dos caras casi
+    // igual sin importar el ángulo con el que llega
+    let scatters = shader_type == Some(ShaderType::Ring);
+
+    // Los cuerpos emisivos brillan con su propio color; el resto suma la luz ambiente
+    // y la difusa de cada luz de la escena, atenuada por los cuerpos que la tapan
+    let lighting = if uniforms.emissive {
+        Vec3::repeat(1.0)
+    } else {
+        uniforms.lights.iter().fold(Vec3::repeat(uniforms.ambient), |sum, light| {
+            let (light_dir, radiance) = light.incident(&fragment.world_position);
+            let diffuse = if scatters { 1.0 } else { dot(&normal, &light_dir) };
+            if diffuse <= 0.0 {
+                return sum;
+            }
+            let mut visibility = light.visibility(&fragment.world_position, &light_dir, &uniforms.occluders);
+            if let Some((clouds, center, radius)) = cloud_shadow {
+                visibility *= clouds.transmittance(&center, radius, &fragment.world_position, &light_dir);
+            }
+            sum + radiance * (diffuse * visibility)
+        })
+    };
+
+    // Partes que brillan con luz propia aunque el cuerpo no sea emisivo (lava, motores)
+    let emission = match shader_type {
+        Some(ShaderType::VolcanicPlanet) => shader::volcanic_planet_emission(&position, time),
+        Some(ShaderType::Spaceship) => shader::spaceship_engine_emission(&fragment.object_position),
+        _ => None,
+    };
+    let color = albedo.scale_channels(lighting.x, lighting.y, lighting.z);
+    let color = match emission {
+        Some(emission) => color + emission,
+        None => color,
+    };
+
+    Some((color * params.brightness).fade(params.opacity))
+}
+
+// La atmósfera no tiene superficie: su color es la luz que dispersa el aire del rayo de vista
+fn shade_atmosphere(fragment: &Fragment, uniforms: &Uniforms) -> Option<Color> {
+    let atmosphere = uniforms.atmosphere.as_ref()?;
+    let center = (uniforms.model_matrix * Vec4::new(0.0, 0.0, 0.0, 1.0)).xyz();
+    let radius = (uniforms.model_matrix * Vec4::new(1.0, 0.0, 0.0, 0.0)).xyz().magnitude();
+    let light = atmosphere.in_scattering(&center, radius, &uniforms.camera_position, &fragment.world_position, &uniforms.lights, &uniforms.occluders);
+    Some(Color::from_float(light.x, light.y, light.z))
+}
+
+// Normal de shading en coordenadas de mundo: la interpolada, o perturbada en espacio
+// del modelo por el normal map y por el relieve procedural del shader
+fn surface_normal(fragment: &Fragment, shader_type: Option<ShaderType>, position: &Vec3, time: f32, uniforms: &Uniforms) -> Vec3 {
+    let bump = uniforms.shader_params.bump;
+    let has_relief = bump > 0.0 && matches!(shader_type, Some(ShaderType::RockyPlanet) | Some(ShaderType::Moon));
+    if uniforms.normal_map.is_none() && !has_relief {
+        return fragment.normal;
+    }
+
+    let mut normal = fragment.object_normal.normalize();
+    if let Some(normal_map) = &uniforms.normal_map {
+        normal = apply_normal_map(&normal, fragment, normal_map);
+    }
+    if has_relief {
+        normal = match shader_type {
+            Some(ShaderType::RockyPlanet) => shader::bump_normal(&normal, position, bump, |p| shader::rocky_planet_height(p, time)),
+            _ => shader::bump_normal(&normal, position, bump, shader::moon_height),
+        };
+    }
+
+    (uniforms.normal_matrix * normal).normalize()
+}
+
+// Normal del normal map llevada a espacio del modelo con la base tangente del fragmento
+fn apply_normal_map(normal: &Vec3, fragment: &Fragment, normal_map: &NormalMap) -> Vec3 {
+    let tangent = fragment.tangent - normal * normal.dot(&fragment.tangent);
+    if tangent.magnitude_squared() < 1e-12 {
+        // Sin dirección de u (polos de la esfera): se deja la normal sin perturbar
+        return *normal;
+    }
+    let tangent = tangent.normalize();
+    let bitangent = normal.cross(&tangent); // Dirección en la que crece v
+
+    let lod = normal_map.texture.level_of_detail(fragment.tex_coords_dx, fragment.tex_coords_dy);
+    let texel = normal_map.texture.sample_rgb(fragment.tex_coords, lod) * 2.0 - Vec3::repeat(1.0);
+
+    // El verde apunta hacia arriba en la imagen, es decir hacia -v
+    let mapped = tangent * (texel.x * normal_map.strength) - bitangent * (texel.y * normal_map.strength) + normal * texel.z;
+    mapped.normalize()
 }
 
 // Shader para la nave espacial
-fn spaceship_shader(position: &Vec3, normal: &Vec3, _light_dir: &Vec3, intensity: f32) -> Color {
+fn spaceship_shader(position: &Vec3) -> Color {
     // Color base dorado metálico
     let base_color = Color::new(200, 170, 50);
     
     // Paneles y detalles
-    let panel_variation = ((position.x * 5.0).sin() * (position.y * 5.0).cos()).abs();
-    let panel_color = if panel_variation > 0.7 {
+    if ((position.x * 5.0).sin() * (position.y * 5.0).cos()).abs() > 0.7 {
         Color::new(220, 190, 70)
     } else {
         base_color
-    };
-    
-    // Iluminación más brillante para que destaque
-    let lit_color = panel_color * intensity.max(0.4);
-    
-    lit_color
+    }
 }
 
-fn calculate_bounding_box(v1: &Vec3, v2: &Vec3, v3: &Vec3) -> (i32, i32, i32, i32) {
-    let min_x = v1.x.min(v2.x).min(v3.x).floor() as i32;
-    let min_y = v1.y.min(v2.y).min(v3.y).floor() as i32;
-    let max_x = v1.x.max(v2.x).max(v3.x).ceil() as i32;
-    let max_y = v1.y.max(v2.y).max(v3.y).ceil() as i32;
+// Bounding box del triángulo, recortada al rectángulo `bounds`
+fn calculate_bounding_box(v1: &Vec3, v2: &Vec3, v3: &Vec3, bounds: PixelRect) -> PixelRect {
+    let min_x = (v1.x.min(v2.x).min(v3.x).floor() as i32).max(bounds.0);
+    let min_y = (v1.y.min(v2.y).min(v3.y).floor() as i32).max(bounds.1);
+    let max_x = (v1.x.max(v2.x).max(v3.x).ceil() as i32).min(bounds.2);
+    let max_y = (v1.y.max(v2.y).max(v3.y).ceil() as i32).min(bounds.3);
     
     (min_x, min_y, max_x, max_y)
 }
@@ -117,6 +377,17 @@ fn barycentric_coordinates(p: &Vec3, a: &Vec3, b: &Vec3, c: &Vec3, area: f32) ->
     (w1, w2, w3)
 }
 
+// Regla top-left: una muestra justo sobre la arista compartida por dos triángulos es solo
+// de uno de ellos, el que tiene esa arista arriba (horizontal, con el triángulo debajo) o a
+// la izquierda (con el triángulo a su derecha). Sin ella se mezcla dos veces en la pasada
+// transparente
+fn is_top_left(start: &Vec3, end: &Vec3, opposite: &Vec3) -> bool {
+    // Normal de la arista hacia el interior del triángulo (y crece hacia abajo)
+    let normal = Vec2::new(start.y - end.y, end.x - start.x);
+    let normal = if normal.dot(&(opposite - start).xy()) < 0.0 { -normal } else { normal };
+    normal.x > 0.0 || (normal.x == 0.0 && normal.y > 0.0)
+}
+
 fn edge_function(a: &Vec3, b: &Vec3, c: &Vec3) -> f32 {
     (c.x - a.x) * (b.y - a.y) - (c.y - a.y) * (b.x - a.x)
 }
\ No newline at end of file
@@ -1,12 +1,15 @@
-use nalgebra_glm::{Vec2, Vec3};
+use nalgebra_glm::{Vec2, Vec3, Vec4};
 use crate::color::Color;
 
 #[derive(Clone, Debug)]
 pub struct Vertex {
   pub position: Vec3,
   pub normal: Vec3,
+  pub tangent: Vec3, // Dirección de +u en espacio del modelo (para normal maps)
   pub tex_coords: Vec2,
   pub color: Color,
+  pub world_position: Vec3,
+  pub clip_position: Vec4,
   pub transformed_position: Vec3,
   pub transformed_normal: Vec3,
 }
@@ -16,8 +19,11 @@ impl Vertex {
     Vertex {
       position,
       normal,
+      tangent: Vec3::new(0.0, 0.0, 0.0),
       tex_coords,
-      color: Color::black(),
+      color: Color::from_float(1.0, 1.0, 1.0),
+      world_position: position,
+      clip_position: Vec4::new(position.x, position.y, position.z, 1.0),
       transformed_position: position,
       transformed_normal: normal,
     }
@@ -27,8 +33,11 @@ impl Vertex {
     Vertex {
       position,
       normal: Vec3::new(0.0, 0.0, 0.0),
+      tangent: Vec3::new(0.0, 0.0, 0.0),
       tex_coords: Vec2::new(0.0, 0.0),
       color,
+      world_position: position,
+      clip_position: Vec4::new(0.0, 0.0, 0.0, 1.0),
       transformed_position: Vec3::new(0.0, 0.0, 0.0),
       transformed_normal: Vec3::new(0.0, 0.0, 0.0),
     }
@@ -45,8 +54,11 @@ impl Default for Vertex {
     Vertex {
       position: Vec3::new(0.0, 0.0, 0.0),
       normal: Vec3::new(0.0, 1.0, 0.0),
+      tangent: Vec3::new(1.0, 0.0, 0.0),
       tex_coords: Vec2::new(0.0, 0.0),
-      color: Color::black(),
+      color: Color::from_float(1.0, 1.0, 1.0),
+      world_position: Vec3::new(0.0, 0.0, 0.0),
+      clip_position: Vec4::new(0.0, 0.0, 0.0, 1.0),
       transformed_position: Vec3::new(0.0, 0.0, 0.0),
       transformed_normal: Vec3::new(0.0, 1.0, 0.0),
     }